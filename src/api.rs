@@ -0,0 +1,117 @@
+use http_body_util::Full;
+use hyper::{
+    Request,
+    body::Bytes,
+    header::{ACCEPT_ENCODING, HeaderName, HeaderValue},
+};
+use tracing::info;
+use url::Url;
+
+use crate::config::{NotaryConfig, ProverConfig};
+use crate::errors::{ProverError, Result};
+use crate::policy::DisclosurePolicy;
+use crate::prover::ProverService;
+
+/// Serialized presentation together with the notary session it came from
+#[derive(Debug)]
+pub struct ProveOutput {
+    /// Session id assigned by the notary, for audit logs
+    pub session_id: String,
+    /// Bincode-serialized presentation
+    pub presentation: Vec<u8>,
+}
+
+/// Notarize a request to `server_url` and return the serialized presentation
+///
+/// Only the fields selected by `policy` are disclosed, see
+/// `DisclosurePolicy::revolut_transaction` for the default policy.
+pub async fn prove(
+    server_url: String,
+    method: String,
+    headers: Vec<String>,
+    body: Option<String>,
+    notary: &NotaryConfig,
+    policy: DisclosurePolicy,
+    config: &ProverConfig,
+) -> Result<ProveOutput> {
+    // Parse the server URL to get host and port information for both HTTP request and TLS connection
+    let server_parsed = Url::parse(&server_url).map_err(ProverError::InvalidServerUrl)?;
+
+    let server_host = server_parsed
+        .host_str()
+        .ok_or(ProverError::MissingAuthority)?;
+
+    let server_port = server_parsed
+        .port()
+        .unwrap_or(if server_parsed.scheme() == "https" {
+            443
+        } else {
+            80
+        });
+
+    // The body is sent byte for byte as given, so it is only checked to be a JSON text,
+    // which is what `parse_request` accepts as a body
+    if let Some(body) = &body {
+        serde_json::from_str::<serde::de::IgnoredAny>(body)
+            .map_err(ProverError::InvalidRequestBody)?;
+    }
+
+    // Build the HTTP request
+    let mut builder = Request::builder()
+        .method(method.as_str())
+        .uri(server_url.as_str())
+        .header("connection", "close")
+        .header("host", server_host.to_string());
+
+    if body.is_some() {
+        builder = builder.header("content-type", "application/json");
+    }
+
+    let body = Bytes::from(body.unwrap_or_default());
+    let mut request = builder
+        .header("Content-Length", body.len().to_string())
+        .body(Full::new(body))
+        .map_err(ProverError::RequestBuildFailed)?;
+
+    // Add custom headers to the request
+    let request_headers = request.headers_mut();
+
+    for header in headers {
+        let (key, value) = header
+            .split_once(':')
+            .ok_or_else(|| ProverError::InvalidHeader(header.clone()))?;
+
+        let key = key
+            .trim()
+            .parse::<HeaderName>()
+            .map_err(|_| ProverError::InvalidHeader(header.clone()))?;
+        let value = value
+            .trim()
+            .parse::<HeaderValue>()
+            .map_err(|_| ProverError::InvalidHeader(header.clone()))?;
+
+        request_headers.insert(key, value);
+    }
+
+    // Compressed responses cannot be disclosed field by field, so always ask for
+    // the identity encoding, even if a custom header asked for something else
+    request_headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+
+    // Headers and body may carry credentials, so only the request line is logged
+    info!(method = %request.method(), path = request.uri().path(), "Notarizing request");
+
+    // Create a prover service, notarize and create presentation
+    let notarized = ProverService::with_policy(policy)
+        .notarize_and_create_presentation(request, config, notary, server_host, server_port)
+        .await?;
+
+    info!(session_id = %notarized.session_id, "Presentation created");
+
+    let serialized_presentation =
+        bincode::serialize(&notarized.presentation).map_err(ProverError::SerializationFailed)?;
+
+    Ok(ProveOutput {
+        session_id: notarized.session_id,
+        presentation: serialized_presentation,
+    })
+}
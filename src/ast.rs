@@ -1,26 +1,87 @@
 use pest::{RuleType, iterators::Pair};
-use std::{collections::HashMap, ops::Range};
+use std::ops::Range;
+
+use crate::selector::FieldSelector;
 
 #[derive(Debug, Clone, Default)]
 pub struct RangedHeader {
     pub range: Range<usize>,
+    /// Range of the value alone, without the name, separator and line ending
+    pub value_range: Range<usize>,
     pub _value: String,
 }
 
+/// Part of a header line to reveal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderPart {
+    /// The whole `name: value` line including its line ending
+    Line,
+    /// Only the value bytes
+    Value,
+}
+
+/// Header fields in transcript order, with case-insensitive lookup
+///
+/// Repeated fields such as `set-cookie` are all kept.
+#[derive(Debug, Clone, Default)]
+pub struct Headers(Vec<(String, RangedHeader)>);
+
+impl Headers {
+    /// Append a header field as it appears in the transcript
+    pub fn push(&mut self, name: String, header: RangedHeader) {
+        self.0.push((name, header));
+    }
+
+    /// First header with the given name
+    pub fn get(&self, name: &str) -> Option<&RangedHeader> {
+        self.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, header)| header)
+    }
+
+    /// Every header with the given name, in transcript order
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a RangedHeader> {
+        self.iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, header)| header)
+    }
+
+    /// Every header with its name as sent, in transcript order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &RangedHeader)> {
+        self.0.iter().map(|(name, header)| (name.as_str(), header))
+    }
+
+    /// Number of header fields
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether there are no header fields
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum RangedValue {
-    Null,
+    Null {
+        range: Range<usize>,
+    },
     Bool {
         range: Range<usize>,
         _value: bool,
     },
     Number {
         range: Range<usize>,
-        _value: f64,
+        /// Number exactly as the server sent it, e.g. `-1050` or `1.5e3`
+        lexeme: String,
     },
     String {
         range: Range<usize>,
-        _value: String,
+        /// Text between the quotes exactly as sent, escapes included
+        raw: String,
+        /// Text with escapes decoded
+        value: String,
     },
     Array {
         range: Range<usize>,
@@ -28,9 +89,28 @@ pub enum RangedValue {
     },
     Object {
         range: Range<usize>,
-        value: HashMap<String, RangedValue>,
+        value: Vec<ObjectEntry>,
+    },
+    /// Value whose bytes were not all disclosed, only found in redacted transcripts
+    Redacted {
+        range: Range<usize>,
     },
 }
+
+/// Member of a JSON object, with the exact span of each of its parts
+#[derive(Debug, Clone)]
+pub struct ObjectEntry {
+    /// Key with escapes decoded
+    pub key: String,
+    /// Span of the whole member, from the key's opening quote to the end of the value
+    pub range: Range<usize>,
+    /// Span of the key including its quotes
+    pub key_range: Range<usize>,
+    /// Offset of the `:` separator
+    pub colon: usize,
+    pub value: RangedValue,
+}
+
 impl Default for RangedValue {
     fn default() -> Self {
         RangedValue::Object {
@@ -44,14 +124,228 @@ impl RangedValue {
     /// Get the range of the current RangedValue.
     pub fn get_range(&self) -> Range<usize> {
         match self {
-            RangedValue::Null => 0..0, // Or handle it differently if needed
-            RangedValue::Bool { range, .. }
+            RangedValue::Null { range }
+            | RangedValue::Bool { range, .. }
             | RangedValue::Number { range, .. }
             | RangedValue::String { range, .. }
             | RangedValue::Array { range, .. }
-            | RangedValue::Object { range, .. } => range.clone(),
+            | RangedValue::Object { range, .. }
+            | RangedValue::Redacted { range } => range.clone(),
+        }
+    }
+
+    /// First value at a dot-separated keypath, in document order.
+    ///
+    /// Arrays are traversed transparently, as in `Searchable::get_field_ranges_for_keypaths`.
+    pub fn get_keypath(&self, keypath: &str) -> Option<&RangedValue> {
+        let (key, rest) = match keypath.split_once('.') {
+            Some((key, rest)) => (key, Some(rest)),
+            None => (keypath, None),
+        };
+
+        match self {
+            RangedValue::Object { value, .. } => value
+                .iter()
+                .filter(|entry| entry.key == key)
+                .find_map(|entry| match rest {
+                    Some(rest) => entry.value.get_keypath(rest),
+                    None => Some(&entry.value),
+                }),
+            RangedValue::Array { value, .. } => {
+                value.iter().find_map(|item| item.get_keypath(keypath))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether this is an explicit JSON `null`.
+    pub fn is_null(&self) -> bool {
+        matches!(self, RangedValue::Null { .. })
+    }
+
+    /// String with escapes decoded.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            RangedValue::String { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Number as an `i64`, if it is an integer written without fraction or exponent
+    /// that fits.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_integer_str()?.parse().ok()
+    }
+
+    /// Number as a `u128`, if it is a non-negative integer written without fraction
+    /// or exponent that fits.
+    pub fn as_u128(&self) -> Option<u128> {
+        self.as_integer_str()?.parse().ok()
+    }
+
+    /// Number in plain decimal notation, exactly as sent, if it has no exponent.
+    pub fn as_decimal_str(&self) -> Option<&str> {
+        match self {
+            RangedValue::Number { lexeme, .. } if !lexeme.contains(['e', 'E']) => Some(lexeme),
+            _ => None,
+        }
+    }
+
+    fn as_integer_str(&self) -> Option<&str> {
+        self.as_decimal_str().filter(|lexeme| !lexeme.contains('.'))
+    }
+
+    /// Ranges of the structure leading from this value down to each of the `targets`.
+    ///
+    /// For every object or array enclosing a target, this is its brackets, commas,
+    /// colons and the whitespace between them, the keys leading to the targets, and
+    /// the delimiters of every other key and value: the quotes of strings, the brackets
+    /// of objects and arrays, and the first byte of numbers, booleans and `null`, which
+    /// gives away the sign and leading digit of a number and the value of a boolean.
+    /// Revealed together with the targets, it lets a verifier re-parse the redacted
+    /// document, tell where each hidden value starts and ends, and confirm the key path
+    /// and array indices leading to each target. Each contiguous run of structure is
+    /// one range.
+    pub fn structure_ranges(&self, targets: &[Range<usize>]) -> Vec<Range<usize>> {
+        let mut pieces = Vec::new();
+        if encloses_any(&self.get_range(), targets) {
+            self.push_structure(targets, &mut pieces);
+        }
+
+        let mut ranges: Vec<Range<usize>> = Vec::with_capacity(pieces.len());
+        for piece in pieces.into_iter().filter(|piece| !piece.is_empty()) {
+            match ranges.last_mut() {
+                Some(last) if last.end == piece.start => last.end = piece.end,
+                _ => ranges.push(piece),
+            }
+        }
+        ranges
+    }
+
+    /// Push the structure of this container in document order, descending into the
+    /// members that enclose a target
+    fn push_structure(&self, targets: &[Range<usize>], ranges: &mut Vec<Range<usize>>) {
+        let range = self.get_range();
+        let mut start = range.start;
+
+        match self {
+            RangedValue::Object { value, .. } => {
+                for entry in value {
+                    let value_range = entry.value.get_range();
+                    if encloses_any(&value_range, targets) {
+                        // On the path to a target, so its key is revealed
+                        ranges.push(start..value_range.start);
+                        entry.value.push_structure(targets, ranges);
+                    } else {
+                        ranges.push(start..entry.key_range.start + 1);
+                        ranges.push(entry.key_range.end - 1..value_range.start);
+                        ranges.extend(entry.value.delimiter_ranges());
+                    }
+                    start = value_range.end;
+                }
+            }
+            RangedValue::Array { value, .. } => {
+                for item in value {
+                    let item_range = item.get_range();
+                    ranges.push(start..item_range.start);
+                    if encloses_any(&item_range, targets) {
+                        item.push_structure(targets, ranges);
+                    } else {
+                        ranges.extend(item.delimiter_ranges());
+                    }
+                    start = item_range.end;
+                }
+            }
+            _ => return,
+        }
+
+        ranges.push(start..range.end);
+    }
+
+    /// Ranges marking where this value starts and ends without revealing its contents
+    pub(crate) fn delimiter_ranges(&self) -> Vec<Range<usize>> {
+        let range = self.get_range();
+        let first = range.start..range.start + 1;
+        match self {
+            RangedValue::String { .. } | RangedValue::Array { .. } | RangedValue::Object { .. } => {
+                vec![first, range.end - 1..range.end]
+            }
+            RangedValue::Null { .. } | RangedValue::Bool { .. } | RangedValue::Number { .. } => {
+                vec![first]
+            }
+            RangedValue::Redacted { .. } => Vec::new(),
+        }
+    }
+}
+
+/// Whether `outer` holds one of the `targets` without being it
+fn encloses_any(outer: &Range<usize>, targets: &[Range<usize>]) -> bool {
+    targets
+        .iter()
+        .any(|target| outer.start <= target.start && target.end <= outer.end && outer != target)
+}
+
+/// Decode the escapes of a JSON string body, RFC 8259 section 7
+///
+/// Expects the text between the quotes as accepted by the grammar. Unpaired
+/// UTF-16 surrogates decode to U+FFFD.
+pub fn decode_json_string(raw: &str) -> String {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            decoded.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('b') => decoded.push('\u{8}'),
+            Some('f') => decoded.push('\u{c}'),
+            Some('n') => decoded.push('\n'),
+            Some('r') => decoded.push('\r'),
+            Some('t') => decoded.push('\t'),
+            Some('u') => {
+                let high = read_hex4(&mut chars);
+                let code = match high {
+                    Some(high @ 0xD800..=0xDBFF) => {
+                        // A high surrogate must be followed by an escaped low surrogate
+                        let mut lookahead = chars.clone();
+                        match (
+                            lookahead.next(),
+                            lookahead.next(),
+                            read_hex4(&mut lookahead),
+                        ) {
+                            (Some('\\'), Some('u'), Some(low @ 0xDC00..=0xDFFF)) => {
+                                chars = lookahead;
+                                Some(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+                            }
+                            _ => None,
+                        }
+                    }
+                    other => other,
+                };
+                decoded.push(
+                    code.and_then(char::from_u32)
+                        .unwrap_or(char::REPLACEMENT_CHARACTER),
+                );
+            }
+            // `\"`, `\\` and `\/` stand for themselves
+            Some(other) => decoded.push(other),
+            None => decoded.push('\\'),
         }
     }
+
+    decoded
+}
+
+fn read_hex4(chars: &mut std::str::Chars) -> Option<u32> {
+    let hex: String = chars.by_ref().take(4).collect();
+    if hex.len() == 4 {
+        u32::from_str_radix(&hex, 16).ok()
+    } else {
+        None
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -62,6 +356,7 @@ pub enum CommonRuleType {
     Number,
     Boolean,
     Null,
+    Redacted,
     Other,
 }
 
@@ -83,17 +378,14 @@ impl CommonParser {
             .ok_or("Missing key in header")?
             .as_str()
             .to_string();
-        let value = inner
-            .next()
-            .ok_or("Missing value in header")?
-            .as_str()
-            .to_string();
+        let value = inner.next().ok_or("Missing value in header")?;
 
         Ok((
             key,
             RangedHeader {
                 range,
-                _value: value,
+                value_range: value.as_span().start()..value.as_span().end(),
+                _value: value.as_str().to_string(),
             },
         ))
     }
@@ -107,55 +399,79 @@ impl CommonParser {
                 value: pair
                     .into_inner()
                     .map(|p| Self::parse_object_entry(p))
-                    .collect::<HashMap<_, _>>(),
+                    .collect(),
             },
             CommonRuleType::Array => RangedValue::Array {
                 range,
                 value: pair.into_inner().map(|p| Self::parse_value(p)).collect(),
             },
-            CommonRuleType::String => RangedValue::String {
-                range,
-                _value: pair
+            CommonRuleType::String => {
+                let raw = pair
                     .into_inner()
                     .next()
                     .map(|p| p.as_str().to_string())
-                    .unwrap_or_default(),
-            },
+                    .unwrap_or_default();
+                RangedValue::String {
+                    range,
+                    value: decode_json_string(&raw),
+                    raw,
+                }
+            }
             CommonRuleType::Number => RangedValue::Number {
                 range,
-                _value: pair.as_str().parse().unwrap_or_default(),
+                lexeme: pair.as_str().to_string(),
             },
             CommonRuleType::Boolean => RangedValue::Bool {
                 range,
                 _value: pair.as_str().parse().unwrap_or_default(),
             },
-            CommonRuleType::Null => RangedValue::Null,
+            CommonRuleType::Null => RangedValue::Null { range },
+            CommonRuleType::Redacted => RangedValue::Redacted { range },
             CommonRuleType::Other => unreachable!("Unexpected rule in parse_value"),
         }
     }
 
-    fn parse_object_entry<R: CommonRule>(pair: Pair<R>) -> (String, RangedValue) {
+    fn parse_object_entry<R: CommonRule>(pair: Pair<R>) -> ObjectEntry {
+        let range = pair.as_span().start()..pair.as_span().end();
         let mut inner_rules = pair.into_inner();
-        let key = inner_rules
-            .next()
-            .expect("Missing key in object entry")
-            .into_inner()
+
+        let key = inner_rules.next().expect("Missing key in object entry");
+        let key_range = key.as_span().start()..key.as_span().end();
+        let key = decode_json_string(
+            key.into_inner()
+                .next()
+                .expect("Missing key in object entry")
+                .as_str(),
+        );
+        let colon = inner_rules
             .next()
-            .expect("Missing key in object entry")
-            .as_str()
-            .to_string();
+            .expect("Missing colon in object entry")
+            .as_span()
+            .start();
         let value = Self::parse_value(inner_rules.next().expect("Missing value in object entry"));
-        (key, value)
+
+        ObjectEntry {
+            key,
+            range,
+            key_range,
+            colon,
+            value,
+        }
     }
 }
 
 pub trait Searchable {
-    fn get_headers(&self) -> &HashMap<String, RangedHeader>;
+    fn get_headers(&self) -> &Headers;
     fn get_content(&self) -> Option<&RangedValue>;
     fn get_additional_ranges(&self) -> Vec<Range<usize>> {
         Vec::new()
     }
 
+    /// Translate a range of the content into the transcript ranges holding its bytes.
+    fn to_transcript_ranges(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        vec![range]
+    }
+
     fn get_all_ranges_for_keypaths(
         &self,
         keypaths: &[&str],
@@ -166,45 +482,115 @@ pub trait Searchable {
         // Add any additional ranges specific to the type
         ranges.extend(self.get_additional_ranges());
 
-        // Check headers for matching keys
-        for (key, header) in self.get_headers() {
-            if headers.contains(&key.as_str()) {
-                ranges.push(header.range.clone());
-            }
-        }
+        // Check headers for matching names
+        ranges.extend(self.get_header_ranges(headers, HeaderPart::Line));
 
         // Recursively search in content for matching key paths
+        ranges.extend(
+            self.get_field_ranges_for_keypaths(keypaths)
+                .into_iter()
+                .flat_map(|(_, range)| self.to_transcript_ranges(range)),
+        );
+
+        ranges
+    }
+
+    /// Get the ranges of every header with one of the given names, compared case-insensitively.
+    fn get_header_ranges(&self, names: &[&str], part: HeaderPart) -> Vec<Range<usize>> {
+        self.get_headers()
+            .iter()
+            .filter(|(key, _)| names.iter().any(|name| name.eq_ignore_ascii_case(key)))
+            .map(|(_, header)| match part {
+                HeaderPart::Line => header.range.clone(),
+                HeaderPart::Value => header.value_range.clone(),
+            })
+            .filter(|range| !range.is_empty())
+            .collect()
+    }
+
+    /// Get the range of every field matching one of the keypaths, labelled with its keypath.
+    ///
+    /// Ranges are offsets into the content, see `to_transcript_ranges`.
+    fn get_field_ranges_for_keypaths(&self, keypaths: &[&str]) -> Vec<(String, Range<usize>)> {
+        let mut fields = Vec::new();
+
         if let Some(content) = self.get_content() {
-            Self::search_content_by_path(keypaths, content, Vec::new(), &mut ranges);
+            Self::search_content_by_path(keypaths, content, Vec::new(), &mut fields);
         }
 
-        ranges
+        fields
+    }
+
+    /// Get the range of every value matched by one of the selectors, labelled with its selector.
+    ///
+    /// Each selector's `DisclosureMode` decides which part of a match is revealed.
+    /// Ranges are offsets into the content, see `to_transcript_ranges`.
+    fn get_field_ranges_for_selectors(
+        &self,
+        selectors: &[FieldSelector],
+    ) -> Vec<(String, Range<usize>)> {
+        let Some(content) = self.get_content() else {
+            return Vec::new();
+        };
+
+        selectors
+            .iter()
+            .flat_map(|field| {
+                field
+                    .selector
+                    .select(content)
+                    .into_iter()
+                    .filter_map(move |matched| {
+                        Some((field.selector.to_string(), matched.range(field.mode)?))
+                    })
+            })
+            .collect()
+    }
+
+    /// Get the transcript ranges of every value matched by one of the selectors.
+    fn get_ranges_for_selectors(&self, selectors: &[FieldSelector]) -> Vec<Range<usize>> {
+        self.get_field_ranges_for_selectors(selectors)
+            .into_iter()
+            .flat_map(|(_, range)| self.to_transcript_ranges(range))
+            .collect()
+    }
+
+    /// Get the transcript ranges of the structure enclosing the content ranges, see
+    /// `RangedValue::structure_ranges`.
+    fn get_structure_ranges(&self, fields: &[Range<usize>]) -> Vec<Range<usize>> {
+        let Some(content) = self.get_content() else {
+            return Vec::new();
+        };
+
+        content
+            .structure_ranges(fields)
+            .into_iter()
+            .flat_map(|range| self.to_transcript_ranges(range))
+            .collect()
     }
 
     fn search_content_by_path(
         keypaths: &[&str],
         content: &RangedValue,
         current_path: Vec<String>,
-        ranges: &mut Vec<Range<usize>>,
+        fields: &mut Vec<(String, Range<usize>)>,
     ) {
         match content {
             RangedValue::Object { value, .. } => {
-                for (key, val) in value {
+                for entry in value {
                     let mut new_path = current_path.clone();
-                    new_path.push(key.clone());
+                    new_path.push(entry.key.clone());
                     let path_str = new_path.join(".");
 
                     if keypaths.contains(&path_str.as_str()) {
-                        let start = val.get_range().start;
-                        let end = val.get_range().end;
-                        ranges.push((start - key.len() - 3)..end);
+                        fields.push((path_str, entry.range.clone()));
                     }
-                    Self::search_content_by_path(keypaths, val, new_path, ranges);
+                    Self::search_content_by_path(keypaths, &entry.value, new_path, fields);
                 }
             }
             RangedValue::Array { value, .. } => {
                 for item in value {
-                    Self::search_content_by_path(keypaths, item, current_path.clone(), ranges);
+                    Self::search_content_by_path(keypaths, item, current_path.clone(), fields);
                 }
             }
             _ => {}
@@ -400,3 +786,47 @@ pub trait Searchable {
 //     let value = parse_value(inner_rules.next().expect("Missing value in object entry"));
 //     (key, value)
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_and_backslash_escapes() {
+        assert_eq!(decode_json_string(r#"say \"hi\""#), r#"say "hi""#);
+        assert_eq!(decode_json_string(r"C:\\temp\\"), r"C:\temp\");
+        assert_eq!(decode_json_string(r"a\/b\tc\nd"), "a/b\tc\nd");
+    }
+
+    #[test]
+    fn unicode_escape() {
+        assert_eq!(decode_json_string(r"caf\u00e9"), "café");
+        assert_eq!(decode_json_string(r"\u00E9"), "é");
+    }
+
+    #[test]
+    fn surrogate_pair() {
+        assert_eq!(decode_json_string(r"\ud83d\ude00!"), "😀!");
+    }
+
+    #[test]
+    fn lone_surrogates_are_replaced() {
+        assert_eq!(decode_json_string(r"\ud83d"), "\u{fffd}");
+        assert_eq!(decode_json_string(r"\ud83dx"), "\u{fffd}x");
+        // The escape after a high surrogate is kept when it is not a low surrogate
+        assert_eq!(decode_json_string(r"\ud83d\u0041"), "\u{fffd}A");
+        assert_eq!(decode_json_string(r"\ude00"), "\u{fffd}");
+    }
+
+    #[test]
+    fn escaped_backslash_before_u() {
+        assert_eq!(decode_json_string(r"\\u0041"), r"\u0041");
+        assert_eq!(decode_json_string(r"\\\u0041"), r"\A");
+    }
+
+    #[test]
+    fn truncated_escapes() {
+        assert_eq!(decode_json_string(r"\u00"), "\u{fffd}");
+        assert_eq!(decode_json_string("end\\"), "end\\");
+    }
+}
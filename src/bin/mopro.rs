@@ -2,7 +2,6 @@ use alloy_primitives::{Address, hex::FromHex};
 use alloy_provider::ProviderBuilder;
 use alloy_sol_types::sol;
 use noir::barretenberg::srs::setup_srs_from_bytecode;
-use noir::barretenberg::verify;
 use noir::{
     barretenberg::{
         prove::prove_ultra_honk_keccak, srs::setup_srs,
@@ -10,91 +9,43 @@ use noir::{
     },
     witness::from_vec_str_to_witness_map,
 };
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs, vec};
-use tlsn_common::transcript;
-use tlsn_core::{
-    attestation::{Extension, Field, Header},
-    connection::{ConnectionInfo, ServerCertCommitment, ServerEphemKey},
-    hash::{Blinder, Hash, HashAlgId},
-    presentation::Presentation,
-    signing::{Signature, VerifyingKey},
-    transcript::{
-        Direction, Idx, PartialTranscript, TranscriptCommitment, hash::PlaintextHashSecret,
-    },
-};
+use std::fs;
+use std::path::PathBuf;
+use tlsn_core::presentation::Presentation;
+use tlsn_revolut::{Error, VerifierError, witness::build_witness};
 
 fn main() {
     let rt = tokio::runtime::Runtime::new().unwrap();
-    rt.block_on(async_main());
+    if let Err(e) = rt.block_on(async_main()) {
+        eprintln!("Error [{}]: {}", e.code(), e.report());
+        std::process::exit(1);
+    }
 }
 
-async fn async_main() {
-    let presentation = std::fs::read("presentation.tlsn").unwrap();
-
-    let presentation: Presentation = bincode::deserialize(&presentation).unwrap();
-    let mut witness: Vec<String> = vec![];
-
-    let json = serde_json::to_value(&presentation).unwrap();
-    let attestation: AttestationProof =
-        serde_json::from_str(&json["attestation"].to_string()).unwrap();
-    let transcript: TranscriptProof =
-        serde_json::from_str(&json["transcript"].to_string()).unwrap();
-
-    let key_ecdsa =
-        k256::ecdsa::VerifyingKey::from_sec1_bytes(&attestation.body.body.verifying_key.data.data)
-            .unwrap();
-    let key = key_ecdsa.to_encoded_point(false).as_bytes().to_vec();
-
-    let signature = attestation.signature.data;
-
-    let message = bcs::to_bytes(&attestation.header).unwrap();
-
-    let header_root = bcs::to_bytes(&attestation.header.root.value).unwrap();
-
-    let verifying_key_data = bcs::to_bytes(&attestation.body.body.verifying_key.data).unwrap();
-    let connection_info_data = bcs::to_bytes(&attestation.body.body.connection_info.data).unwrap();
-    let server_ephemeral_key_data =
-        bcs::to_bytes(&attestation.body.body.server_ephemeral_key.data).unwrap();
-    let cert_commitment_data = bcs::to_bytes(&attestation.body.body.cert_commitment.data).unwrap();
-    println!("cert_commitment_data: {:?}", cert_commitment_data);
-    let transcript_commitments_data =
-        bcs::to_bytes(&attestation.body.body.transcript_commitments[0]).unwrap();
-    println!(
-        "transcript_commitments_data: {:?}",
-        transcript_commitments_data
-    );
-
-    let transcript_data = transcript.transcript.received_unsafe();
-    let transcript_blinder = transcript.hash_secrets[0].blinder.as_bytes();
-
-    witness.extend(key[1..].iter().map(|n| n.to_string()));
-    witness.extend(message.iter().map(|n| n.to_string()));
-    witness.extend(signature.iter().map(|n| n.to_string()));
-    witness.extend(header_root[1..].iter().map(|n| n.to_string()));
-    witness.extend(verifying_key_data.iter().map(|n| n.to_string()));
-    witness.extend(connection_info_data.iter().map(|n| n.to_string()));
-    witness.extend(server_ephemeral_key_data.iter().map(|n| n.to_string()));
-    witness.extend(cert_commitment_data.iter().map(|n| n.to_string()));
-    witness.extend(
-        transcript_commitments_data[4..]
-            .iter()
-            .map(|n| n.to_string()),
-    );
-    witness.extend(transcript_data.iter().map(|n| n.to_string()));
-    witness.extend(transcript_blinder.iter().map(|n| n.to_string()));
+async fn async_main() -> Result<(), Error> {
+    let path = PathBuf::from("presentation.tlsn");
+    let presentation =
+        std::fs::read(&path).map_err(|source| VerifierError::ReadFailed { path, source })?;
+
+    let presentation: Presentation =
+        bincode::deserialize(&presentation).map_err(VerifierError::DeserializationFailed)?;
+    let witness = build_witness(&presentation)?;
 
     // Verify presentation
 
-    println!("Witness: {:?}", witness);
+    println!("Witness built with {} entries", witness.len());
 
     println!("Verifying presentation");
 
     let verify = verify_presentation(witness).await;
     println!("✔ Verification process started {:?}", verify);
+
+    Ok(())
 }
 
 async fn verify_presentation(witness: Vec<String>) {
+    // The circuit must be compiled for the fields disclosed in the presentation,
+    // see `build_witness` for the witness layout
     let json_content = fs::read_to_string("target/noir_verify_presentation.json").unwrap();
     let json: serde_json::Value = serde_json::from_str(&json_content).unwrap();
     println!("✔ JSON file loaded");
@@ -163,63 +114,3 @@ async fn verify_proof(proof: Vec<u8>) -> bool {
 
     call_result
 }
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-
-pub struct AttestationProof {
-    pub signature: Signature,
-    pub header: Header,
-    pub body: BodyProof,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BodyProof {
-    pub body: Body,
-    pub proof: MerkleProof,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MerkleProof {
-    alg: HashAlgId,
-    leaf_count: usize,
-    proof: rs_merkle::MerkleProof<Hash>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Body {
-    pub verifying_key: Field<VerifyingKey>,
-    pub connection_info: Field<ConnectionInfo>,
-    pub server_ephemeral_key: Field<ServerEphemKey>,
-    pub cert_commitment: Field<ServerCertCommitment>,
-    pub extensions: Vec<Field<Extension>>,
-    pub transcript_commitments: Vec<Field<TranscriptCommitment>>,
-}
-
-#[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct TranscriptProof {
-    pub transcript: PartialTranscript,
-    pub encoding_proof: Option<EncodingProof>,
-    pub hash_secrets: Vec<PlaintextHashSecret>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EncodingProof {
-    pub inclusion_proof: MerkleProof,
-    pub openings: HashMap<usize, Opening>,
-}
-
-#[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct Opening {
-    pub direction: Direction,
-    pub idx: Idx,
-    pub blinder: Blinder,
-}
-
-#[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct TranscriptOpening {
-    pub id: usize,
-    pub direction: usize,
-    pub data: Vec<u8>,
-    pub blinder: Vec<u8>,
-    pub position: usize,
-}
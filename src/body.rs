@@ -0,0 +1,284 @@
+use crate::errors::ParserError;
+use std::ops::Range;
+
+/// Contiguous run of body bytes that appears unchanged in the raw transcript
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Segment {
+    /// Offset in the decoded body
+    body_start: usize,
+    /// Offset in the raw transcript
+    raw_start: usize,
+    len: usize,
+}
+
+/// Decoded HTTP message body with the mapping back to raw transcript offsets
+///
+/// A `Content-Length` or close-delimited body is a single segment. A chunked
+/// body has one segment per chunk, with the chunk framing in between.
+#[derive(Debug, Clone, Default)]
+pub struct BodyMap {
+    segments: Vec<Segment>,
+    /// Raw transcript range from the end of the header section onwards
+    span: Range<usize>,
+}
+
+impl BodyMap {
+    /// Decode a body that starts at `raw_start` in the transcript
+    ///
+    /// `raw` is everything after the header section. Returns the decoded body
+    /// bytes together with the offset mapping.
+    pub fn decode(
+        raw: &[u8],
+        raw_start: usize,
+        chunked: bool,
+        content_length: Option<usize>,
+    ) -> Result<(Vec<u8>, Self), ParserError> {
+        if chunked {
+            return Self::decode_chunked(raw, raw_start);
+        }
+
+        let len = match content_length {
+            Some(len) if len > raw.len() => {
+                return Err(ParserError::InvalidBody(format!(
+                    "Content-Length is {} but only {} body bytes were received",
+                    len,
+                    raw.len()
+                )));
+            }
+            Some(len) => len,
+            // Without a length the body runs until the server closes the connection
+            None => raw.len(),
+        };
+
+        let map = Self {
+            segments: vec![Segment {
+                body_start: 0,
+                raw_start,
+                len,
+            }],
+            span: raw_start..raw_start + raw.len(),
+        };
+
+        Ok((raw[..len].to_vec(), map))
+    }
+
+    /// Decode a `Transfer-Encoding: chunked` body, see RFC 9112 section 7.1
+    fn decode_chunked(raw: &[u8], raw_start: usize) -> Result<(Vec<u8>, Self), ParserError> {
+        let mut body = Vec::new();
+        let mut segments = Vec::new();
+        let mut pos = 0;
+
+        loop {
+            let line_end = find_crlf(raw, pos)
+                .ok_or_else(|| ParserError::InvalidBody("unterminated chunk size line".into()))?;
+            let size = parse_chunk_size(&raw[pos..line_end])?;
+            pos = line_end + 2;
+
+            if size == 0 {
+                // Trailer fields are not part of the body
+                break;
+            }
+
+            let data_end = pos
+                .checked_add(size)
+                .filter(|&end| end <= raw.len())
+                .ok_or_else(|| ParserError::InvalidBody("chunk exceeds received data".into()))?;
+
+            segments.push(Segment {
+                body_start: body.len(),
+                raw_start: raw_start + pos,
+                len: size,
+            });
+            body.extend_from_slice(&raw[pos..data_end]);
+
+            if !raw[data_end..].starts_with(b"\r\n") {
+                return Err(ParserError::InvalidBody(
+                    "chunk data not followed by CRLF".into(),
+                ));
+            }
+            pos = data_end + 2;
+        }
+
+        Ok((
+            body,
+            Self {
+                segments,
+                span: raw_start..raw_start + raw.len(),
+            },
+        ))
+    }
+
+    /// Translate a range of the decoded body into the raw transcript ranges holding it
+    ///
+    /// A range that crosses chunk boundaries is split so the chunk framing in
+    /// between is left out.
+    pub fn raw_ranges(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        self.segments
+            .iter()
+            .filter_map(|s| {
+                let start = range.start.max(s.body_start);
+                let end = range.end.min(s.body_start + s.len);
+                (start < end)
+                    .then(|| s.raw_start + start - s.body_start..s.raw_start + end - s.body_start)
+            })
+            .collect()
+    }
+
+    /// Raw transcript range of the body, framing included
+    pub fn raw_span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Length of the decoded body
+    pub fn len(&self) -> usize {
+        self.segments.iter().map(|s| s.len).sum()
+    }
+
+    /// Whether the decoded body is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Raw transcript ranges after the header section that hold no body bytes
+    ///
+    /// These are the chunk size lines, chunk delimiters and trailer section of a
+    /// chunked body, and anything received after a `Content-Length` body.
+    pub fn framing_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut start = self.span.start;
+        for s in &self.segments {
+            ranges.push(start..s.raw_start);
+            start = s.raw_start + s.len;
+        }
+        ranges.push(start..self.span.end);
+
+        ranges.retain(|range| !range.is_empty());
+        ranges
+    }
+}
+
+/// Position of the next CRLF at or after `from`
+fn find_crlf(raw: &[u8], from: usize) -> Option<usize> {
+    raw.get(from..)?
+        .windows(2)
+        .position(|w| w == b"\r\n")
+        .map(|i| from + i)
+}
+
+/// Parse a chunk size line, ignoring chunk extensions
+fn parse_chunk_size(line: &[u8]) -> Result<usize, ParserError> {
+    let line = std::str::from_utf8(line)
+        .map_err(|_| ParserError::InvalidBody("chunk size is not ASCII".into()))?;
+    let size = line.split(';').next().unwrap_or_default().trim();
+
+    usize::from_str_radix(size, 16)
+        .map_err(|_| ParserError::InvalidBody(format!("invalid chunk size '{}'", size)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bytes of the transcript covered by `ranges`, concatenated
+    fn collect(transcript: &[u8], ranges: &[Range<usize>]) -> Vec<u8> {
+        ranges
+            .iter()
+            .flat_map(|range| transcript[range.clone()].iter().copied())
+            .collect()
+    }
+
+    /// Body ranges and framing ranges together cover the raw span exactly once
+    fn assert_partition(map: &BodyMap) {
+        let mut ranges = map.raw_ranges(0..map.len());
+        ranges.extend(map.framing_ranges());
+        ranges.sort_by_key(|range| range.start);
+
+        let mut pos = map.raw_span().start;
+        for range in ranges {
+            assert_eq!(range.start, pos, "gap or overlap at {pos}");
+            pos = range.end;
+        }
+        assert_eq!(pos, map.raw_span().end);
+    }
+
+    #[test]
+    fn value_spanning_a_chunk_boundary() {
+        let head = b"HTTP/1.1 200 OK\r\n\r\n";
+        let raw = b"7\r\n{\"a\":\"x\r\n4\r\nyz\"}\r\n0\r\n\r\n";
+        let transcript = [&head[..], &raw[..]].concat();
+
+        let (body, map) = BodyMap::decode(raw, head.len(), true, None).unwrap();
+        assert_eq!(body, b"{\"a\":\"xyz\"}");
+
+        // The string value is split around the framing of the second chunk
+        let ranges = map.raw_ranges(5..10);
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(collect(&transcript, &ranges), b"\"xyz\"");
+        assert_eq!(
+            collect(&transcript, &map.framing_ranges()),
+            b"7\r\n\r\n4\r\n\r\n0\r\n\r\n"
+        );
+        assert_partition(&map);
+    }
+
+    #[test]
+    fn chunk_extensions_and_trailers() {
+        let raw = b"2;name=\"value\"\r\n[1\r\n1 ; last\r\n]\r\n0\r\nExpires: never\r\n\r\n";
+
+        let (body, map) = BodyMap::decode(raw, 0, true, None).unwrap();
+        assert_eq!(body, b"[1]");
+        assert_eq!(map.raw_ranges(0..3), [16..18, 30..31]);
+        assert_eq!(
+            collect(raw, &map.framing_ranges()),
+            b"2;name=\"value\"\r\n\r\n1 ; last\r\n\r\n0\r\nExpires: never\r\n\r\n"
+        );
+        assert_partition(&map);
+    }
+
+    #[test]
+    fn invalid_chunks() {
+        assert!(BodyMap::decode(b"zz\r\n{}\r\n0\r\n\r\n", 0, true, None).is_err());
+        assert!(BodyMap::decode(b"5\r\n{}\r\n0\r\n\r\n", 0, true, None).is_err());
+        assert!(BodyMap::decode(b"2\r\n{}0\r\n\r\n", 0, true, None).is_err());
+        assert!(BodyMap::decode(b"2\r\n{}\r\n", 0, true, None).is_err());
+    }
+
+    #[test]
+    fn content_length_body() {
+        let head = b"HTTP/1.1 200 OK\r\n\r\n";
+        let raw = b"{\"a\":1}\r\n";
+        let transcript = [&head[..], &raw[..]].concat();
+
+        let (body, map) = BodyMap::decode(raw, head.len(), false, Some(7)).unwrap();
+        assert_eq!(body, b"{\"a\":1}");
+        assert_eq!(collect(&transcript, &map.raw_ranges(0..7)), b"{\"a\":1}");
+        assert_eq!(collect(&transcript, &map.raw_ranges(5..6)), b"1");
+        // Bytes after the declared length are framing, not body
+        assert_eq!(collect(&transcript, &map.framing_ranges()), b"\r\n");
+        assert_partition(&map);
+
+        assert!(BodyMap::decode(raw, head.len(), false, Some(10)).is_err());
+    }
+
+    #[test]
+    fn close_delimited_body() {
+        let head = b"HTTP/1.1 200 OK\r\n\r\n";
+        let raw = b"{\"a\":[1,2]}";
+        let transcript = [&head[..], &raw[..]].concat();
+
+        let (body, map) = BodyMap::decode(raw, head.len(), false, None).unwrap();
+        assert_eq!(body, raw);
+        assert_eq!(map.len(), raw.len());
+        assert_eq!(map.raw_span(), head.len()..transcript.len());
+        assert_eq!(collect(&transcript, &map.raw_ranges(5..10)), b"[1,2]");
+        assert!(map.framing_ranges().is_empty());
+        assert_partition(&map);
+    }
+
+    #[test]
+    fn empty_ranges() {
+        let (_, map) = BodyMap::decode(b"2\r\n{}\r\n0\r\n\r\n", 0, true, None).unwrap();
+        assert!(map.raw_ranges(1..1).is_empty());
+        assert!(map.raw_ranges(2..5).is_empty());
+    }
+}
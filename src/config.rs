@@ -1,13 +1,127 @@
+use crate::errors::ConfigError;
+use crate::trust::TrustStore;
+use std::net::IpAddr;
+use url::Url;
+
 /// Configuration for the prover service
+#[derive(Debug, Clone)]
 pub struct ProverConfig {
     /// Maximum bytes of data that can be sent
     pub max_sent_data: usize,
     /// Maximum bytes of data that can be received
     pub max_recv_data: usize,
+    /// Root certificates trusted for the server connection
+    pub trust_store: TrustStore,
 }
 
 /// Default prover configuration
 pub const PROVER_CONFIG: ProverConfig = ProverConfig {
     max_sent_data: 4096,
     max_recv_data: 16384,
+    trust_store: TrustStore::webpki(),
 };
+
+/// Connection settings for the notary server
+#[derive(Debug, Clone)]
+pub struct NotaryConfig {
+    /// Notary server host
+    pub host: String,
+    /// Notary server port
+    pub port: u16,
+    /// Connect to the notary over TLS
+    pub tls: bool,
+    /// Root certificates trusted for the notary TLS connection
+    pub trust_store: TrustStore,
+    /// API key sent to notaries that require authorization
+    pub api_key: Option<String>,
+    /// Path prefix of the notary API, e.g. `v1` for `https://notary.example/v1`
+    pub path_prefix: String,
+    /// Seconds to wait for the notary to accept the session request
+    pub request_timeout: usize,
+}
+
+/// Default seconds to wait for the notary to accept a session request
+pub const DEFAULT_NOTARY_REQUEST_TIMEOUT: usize = 60;
+
+impl NotaryConfig {
+    /// Build a notary configuration from its URL
+    ///
+    /// TLS is enabled for `https` URLs. Plain `http` is only accepted for a notary
+    /// running on the local machine. The URL path is used as the API path prefix.
+    pub fn from_url(notary_url: &str) -> Result<Self, ConfigError> {
+        let parsed = Url::parse(notary_url).map_err(|source| ConfigError::InvalidNotaryUrl {
+            url: notary_url.to_string(),
+            source,
+        })?;
+
+        let tls = match parsed.scheme() {
+            "https" => true,
+            "http" => false,
+            scheme => {
+                return Err(ConfigError::NotaryUrlNotAllowed(format!(
+                    "unsupported scheme '{}'",
+                    scheme
+                )));
+            }
+        };
+
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| ConfigError::NotaryUrlNotAllowed("no host found".to_string()))?
+            .to_string();
+
+        if !tls && !is_local_host(&host) {
+            return Err(ConfigError::NotaryUrlNotAllowed(format!(
+                "TLS is required for non-local notary '{}', use an https URL",
+                host
+            )));
+        }
+
+        let port = parsed.port().unwrap_or(if tls { 443 } else { 80 });
+        let path_prefix = parsed.path().trim_matches('/').to_string();
+
+        Ok(Self {
+            host,
+            port,
+            tls,
+            trust_store: TrustStore::webpki(),
+            api_key: None,
+            path_prefix,
+            request_timeout: DEFAULT_NOTARY_REQUEST_TIMEOUT,
+        })
+    }
+
+    /// Use a custom trust store for the notary TLS connection
+    pub fn with_trust_store(mut self, trust_store: TrustStore) -> Self {
+        self.trust_store = trust_store;
+        self
+    }
+
+    /// Authorize notary sessions with an API key
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Override the API path prefix taken from the notary URL
+    pub fn with_path_prefix(mut self, path_prefix: impl Into<String>) -> Self {
+        self.path_prefix = path_prefix.into();
+        self
+    }
+
+    /// Set how many seconds to wait for the notary to accept the session request
+    pub fn with_request_timeout(mut self, seconds: usize) -> Self {
+        self.request_timeout = seconds;
+        self
+    }
+}
+
+/// Whether a host refers to the local machine
+fn is_local_host(host: &str) -> bool {
+    host == "localhost"
+        || host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
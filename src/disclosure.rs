@@ -0,0 +1,288 @@
+//! Selection of the transcript ranges a disclosure policy reveals.
+
+use std::ops::Range;
+
+use crate::ast::{HeaderPart, Searchable};
+use crate::errors::ParserError;
+use crate::policy::DisclosurePolicy;
+use crate::request::parse_request;
+use crate::response::{check_body_encoding, parse_response};
+
+/// Get the ranges of a received transcript to commit to and reveal.
+///
+/// Each field, header and structure range gets its own commitment, so ranges are
+/// kept as they are and only exact duplicates are dropped.
+pub fn received_ranges(
+    data: &[u8],
+    policy: &DisclosurePolicy,
+) -> Result<Vec<Range<usize>>, ParserError> {
+    // A compressed body is not UTF-8, so check the headers first
+    check_body_encoding(data)?;
+
+    // Get the received transcript data as a UTF-8 string
+    let recv_string = String::from_utf8(data.to_vec())?;
+
+    // Parse the response
+    let response = parse_response(&recv_string)?;
+
+    // Get the ranges to reveal
+    let mut ranges = response
+        .get_all_ranges_for_keypaths(&policy.response_keypaths(), &policy.response_headers());
+    ranges.extend(response.get_header_ranges(&policy.response_header_values(), HeaderPart::Value));
+    ranges.extend(response.get_ranges_for_selectors(&policy.response_selectors));
+
+    // Reveal the structure enclosing each body field so its path can be confirmed
+    if policy.reveal_structure {
+        let fields: Vec<Range<usize>> = response
+            .get_field_ranges_for_keypaths(&policy.response_keypaths())
+            .into_iter()
+            .chain(response.get_field_ranges_for_selectors(&policy.response_selectors))
+            .map(|(_, range)| range)
+            .collect();
+        ranges.extend(response.get_structure_ranges(&fields));
+        ranges.extend(response.get_framing_ranges());
+    }
+
+    Ok(dedup_ranges(ranges))
+}
+
+/// Get the ranges of a sent transcript to commit to and reveal.
+///
+/// Ranges overlapping a credential-bearing header are never returned, see
+/// `DisclosurePolicy::is_redacted_request_header`.
+pub fn sent_ranges(
+    data: &[u8],
+    policy: &DisclosurePolicy,
+) -> Result<Vec<Range<usize>>, ParserError> {
+    // Get the sent transcript data as a UTF-8 string
+    let sent_string = String::from_utf8(data.to_vec())?;
+
+    // Parse the request
+    let request = parse_request(&sent_string)?;
+
+    // Get the ranges to reveal
+    let mut ranges =
+        request.get_all_ranges_for_keypaths(&policy.request_keypaths(), &policy.request_headers());
+    ranges.extend(request.get_header_ranges(&policy.request_header_values(), HeaderPart::Value));
+    ranges.extend(request.get_ranges_for_selectors(&policy.request_selectors));
+    ranges.extend(request.get_request_line_ranges(&policy.request_line));
+
+    // Reveal the structure enclosing each body field so its path can be confirmed
+    if policy.reveal_structure {
+        let fields: Vec<Range<usize>> = request
+            .get_field_ranges_for_keypaths(&policy.request_keypaths())
+            .into_iter()
+            .chain(request.get_field_ranges_for_selectors(&policy.request_selectors))
+            .map(|(_, range)| range)
+            .collect();
+        ranges.extend(request.get_structure_ranges(&fields));
+    }
+
+    // Never reveal anything overlapping a credential-bearing header
+    let redacted: Vec<Range<usize>> = request
+        .headers
+        .iter()
+        .filter(|(name, _)| policy.is_redacted_request_header(name))
+        .map(|(_, header)| header.range.clone())
+        .collect();
+    ranges.retain(|range| {
+        !redacted
+            .iter()
+            .any(|hidden| range.start < hidden.end && hidden.start < range.end)
+    });
+
+    Ok(dedup_ranges(ranges))
+}
+
+/// Sort ranges and drop empty and repeated ones, so notarization and presentation
+/// agree on them. Overlapping ranges are kept, each is its own commitment.
+fn dedup_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.retain(|range| !range.is_empty());
+    ranges.sort_by_key(|range| (range.start, range.end));
+    ranges.dedup();
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selector::{DisclosureMode, FieldSelector, Selector};
+
+    /// Response transcript with the given JSON body
+    fn response(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    /// Body of the transcript as revealed by the ranges, with hidden bytes as `.`
+    fn revealed_body(transcript: &str, body: &str, ranges: &[Range<usize>]) -> String {
+        let body_start = transcript.len() - body.len();
+        transcript
+            .char_indices()
+            .skip(body_start)
+            .map(|(i, c)| {
+                if ranges.iter().any(|range| range.contains(&i)) {
+                    c
+                } else {
+                    '.'
+                }
+            })
+            .collect()
+    }
+
+    fn selector_policy(selector: &str, mode: DisclosureMode) -> DisclosurePolicy {
+        DisclosurePolicy {
+            response_selectors: vec![FieldSelector {
+                selector: Selector::parse(selector).unwrap(),
+                mode,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn hidden_siblings_only_reveal_their_delimiters() {
+        let body = r#"{"id":"t1","balance":1234,"fee":{"a":1},"amount":100,"ok":true}"#;
+        let transcript = response(body);
+        let policy = DisclosurePolicy {
+            response_keypaths: vec!["amount".to_string()],
+            ..Default::default()
+        };
+
+        let ranges = received_ranges(transcript.as_bytes(), &policy).unwrap();
+        assert_eq!(
+            revealed_body(&transcript, body, &ranges),
+            r#"{"..":"..",".......":1...,"...":{.....},"amount":100,"..":t...}"#
+        );
+    }
+
+    #[test]
+    fn keys_on_the_path_are_revealed() {
+        let body = r#"{"x":12,"recipient":{"id":"r1","code":"c"},"y":[1,[2]]}"#;
+        let transcript = response(body);
+        let policy = DisclosurePolicy {
+            response_keypaths: vec!["recipient.code".to_string()],
+            ..Default::default()
+        };
+
+        let ranges = received_ranges(transcript.as_bytes(), &policy).unwrap();
+        assert_eq!(
+            revealed_body(&transcript, body, &ranges),
+            r#"{".":1.,"recipient":{"..":"..","code":"c"},".":[.....]}"#
+        );
+    }
+
+    #[test]
+    fn disclosure_modes_reveal_different_bytes() {
+        let body = r#"{"id":"t1","amount":100}"#;
+        let transcript = response(body);
+
+        for (mode, expected) in [
+            (DisclosureMode::Member, r#"{"..":"..","amount":100}"#),
+            (DisclosureMode::Value, r#"{"..":"..","......":100}"#),
+            (DisclosureMode::Key, r#"{"..":"..","amount":1..}"#),
+        ] {
+            let policy = selector_policy("$.amount", mode);
+            let ranges = received_ranges(transcript.as_bytes(), &policy).unwrap();
+            assert_eq!(revealed_body(&transcript, body, &ranges), expected);
+        }
+    }
+
+    #[test]
+    fn compressed_bodies_are_rejected_before_decoding() {
+        // A gzip header followed by deflate data, not valid UTF-8
+        let gzip: &[u8] = &[
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xab,
+        ];
+
+        for (headers, coding) in [
+            ("content-encoding: gzip\r\n", "gzip"),
+            ("Content-Encoding: identity, br\r\n", "br"),
+            ("transfer-encoding: gzip, chunked\r\n", "gzip"),
+        ] {
+            let mut transcript = format!("HTTP/1.1 200 OK\r\n{headers}\r\n").into_bytes();
+            transcript.extend_from_slice(gzip);
+
+            let policy = DisclosurePolicy::revolut_transaction();
+            match received_ranges(&transcript, &policy) {
+                Err(ParserError::UnsupportedEncoding(found)) => assert_eq!(found, coding),
+                result => panic!("expected UnsupportedEncoding({coding}), got {result:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn credential_headers_are_never_revealed() {
+        let transcript = "GET /api/retail/transaction/5 HTTP/1.1\r\n\
+                          host: app.revolut.com\r\n\
+                          Cookie: session=s3cr3t\r\n\
+                          AUTHORIZATION: Bearer t0k3n\r\n\
+                          proxy-authorization: Basic cHJveHk=\r\n\
+                          X-Device-Id: d3v1c3\r\n\
+                          x-api-key: k3y\r\n\
+                          accept: application/json\r\n\r\n";
+        let names = [
+            "cookie",
+            "Authorization",
+            "PROXY-AUTHORIZATION",
+            "x-device-id",
+            "X-Api-Key",
+            "accept",
+            "host",
+        ];
+        let policy = DisclosurePolicy {
+            request_headers: names.map(String::from).to_vec(),
+            request_header_values: names.map(String::from).to_vec(),
+            redacted_request_headers: vec!["X-API-KEY".to_string()],
+            ..Default::default()
+        };
+
+        let ranges = sent_ranges(transcript.as_bytes(), &policy).unwrap();
+        let revealed: String = transcript
+            .char_indices()
+            .filter(|(i, _)| ranges.iter().any(|range| range.contains(i)))
+            .map(|(_, c)| c)
+            .collect();
+
+        for secret in ["s3cr3t", "t0k3n", "cHJveHk=", "d3v1c3", "k3y"] {
+            assert!(!revealed.contains(secret), "{secret} was revealed");
+        }
+        for name in [
+            "Cookie",
+            "AUTHORIZATION",
+            "proxy-authorization",
+            "X-Device-Id",
+            "x-api-key",
+        ] {
+            assert!(!revealed.contains(name), "{name} was revealed");
+        }
+        assert!(revealed.contains("host: app.revolut.com"));
+        assert!(revealed.contains("accept: application/json"));
+    }
+
+    #[test]
+    fn each_field_is_committed_on_its_own() {
+        let body = r#"{"state":"COMPLETED","amount":100}"#;
+        let transcript = response(body);
+        let policy = DisclosurePolicy {
+            response_keypaths: vec!["state".to_string(), "amount".to_string()],
+            ..Default::default()
+        };
+
+        let ranges = received_ranges(transcript.as_bytes(), &policy).unwrap();
+        for field in [r#""state":"COMPLETED""#, r#""amount":100"#] {
+            let start = transcript.find(field).unwrap();
+            assert!(
+                ranges.contains(&(start..start + field.len())),
+                "{field} should have its own range"
+            );
+        }
+
+        let mut deduped = ranges.clone();
+        deduped.dedup();
+        assert_eq!(deduped, ranges);
+    }
+}
@@ -1,13 +1,151 @@
+use crate::redacted::Rule as RedactedRule;
 use crate::request::Rule as RequestRule;
 use crate::response::Rule as ResponseRule;
+use crate::selector::Rule as SelectorRule;
 use hyper::Error as HyperError;
 use pest::error::Error as PestError;
 use std::io;
+use std::path::PathBuf;
 use thiserror::Error;
 use tlsn_common::config::ProtocolConfigBuilderError;
-use tlsn_core::ProveConfigBuilderError;
+use tlsn_core::presentation::PresentationError;
+use tlsn_core::transcript::Direction;
 use tlsn_prover::ProverError as TlsnProverError;
 
+/// Boxed error from a dependency, kept as the source of a wrapping error
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Top-level error of the crate
+///
+/// Every operation returns the sub-error of its own stage, which converts into
+/// this type with `?`. Operations spanning several stages, like notarizing or
+/// verifying, return this type. Match on the variant to find the failing stage
+/// and on the sub-error to find the cause.
+///
+/// Messages do not repeat the error they wrap, it is returned by `source`, see
+/// `Error::report` for the full chain.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// Configuration, trust store or disclosure policy error
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    /// Error while notarizing and creating a presentation
+    #[error(transparent)]
+    Prover(#[from] ProverError),
+
+    /// Error while verifying a presentation
+    #[error(transparent)]
+    Verifier(#[from] VerifierError),
+
+    /// Error while parsing an HTTP transcript
+    #[error(transparent)]
+    Parser(#[from] ParserError),
+
+    /// Error while building the circuit witness
+    #[error(transparent)]
+    Witness(#[from] WitnessError),
+}
+
+impl Error {
+    /// Stable machine-readable code identifying the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Config(e) => e.code(),
+            Error::Prover(e) => e.code(),
+            Error::Verifier(e) => e.code(),
+            Error::Parser(e) => e.code(),
+            Error::Witness(e) => e.code(),
+        }
+    }
+
+    /// Message of the error followed by the messages of its causes, separated by `: `
+    pub fn report(&self) -> String {
+        let mut report = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(cause) = source {
+            report.push_str(": ");
+            report.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        report
+    }
+}
+
+/// Errors in user-supplied configuration
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// Notary URL could not be parsed
+    #[error("Invalid notary URL '{url}'")]
+    InvalidNotaryUrl {
+        url: String,
+        #[source]
+        source: url::ParseError,
+    },
+
+    /// Notary URL is well formed but not allowed, e.g. plain HTTP to a remote host
+    #[error("Notary URL not allowed: {0}")]
+    NotaryUrlNotAllowed(String),
+
+    /// Notary client could not be built from the notary configuration
+    #[error("Invalid notary client configuration")]
+    InvalidNotaryClient(#[source] BoxError),
+
+    /// Notarization request could not be built from the prover configuration
+    #[error("Invalid notarization request")]
+    InvalidNotarizationRequest(#[source] BoxError),
+
+    /// Root certificate could not be decoded or added to the trust store
+    #[error("Invalid root certificate from {origin}")]
+    InvalidCertificate {
+        origin: String,
+        #[source]
+        source: BoxError,
+    },
+
+    /// Certificate file holds no certificate
+    #[error("No CERTIFICATE entries found in {0}")]
+    NoCertificates(String),
+
+    /// Trust store ended up without any root certificate
+    #[error("Trust store has no root certificates")]
+    EmptyTrustStore,
+
+    /// Disclosure policy is not valid JSON for a policy
+    #[error("Failed to parse disclosure policy")]
+    PolicyParseFailed(#[source] serde_json::Error),
+
+    /// Disclosure policy parsed but is not allowed
+    #[error("Invalid disclosure policy: {0}")]
+    InvalidDisclosurePolicy(String),
+
+    /// Configuration file could not be read
+    #[error("Failed to read '{}'", path.display())]
+    ReadFailed {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl ConfigError {
+    /// Stable machine-readable code identifying the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfigError::InvalidNotaryUrl { .. } => "INVALID_NOTARY_URL",
+            ConfigError::NotaryUrlNotAllowed(_) => "NOTARY_URL_NOT_ALLOWED",
+            ConfigError::InvalidNotaryClient(_) => "INVALID_NOTARY_CLIENT",
+            ConfigError::InvalidNotarizationRequest(_) => "INVALID_NOTARIZATION_REQUEST",
+            ConfigError::InvalidCertificate { .. } => "INVALID_CERTIFICATE",
+            ConfigError::NoCertificates(_) => "NO_CERTIFICATES",
+            ConfigError::EmptyTrustStore => "EMPTY_TRUST_STORE",
+            ConfigError::PolicyParseFailed(_) => "POLICY_PARSE_FAILED",
+            ConfigError::InvalidDisclosurePolicy(_) => "INVALID_DISCLOSURE_POLICY",
+            ConfigError::ReadFailed { .. } => "CONFIG_READ_FAILED",
+        }
+    }
+}
+
 /// Main error type for internal prover operations
 #[derive(Error, Debug)]
 pub enum ProverError {
@@ -19,41 +157,66 @@ pub enum ProverError {
     #[error("Request URI does not have an authority or host")]
     MissingAuthority,
 
-    /// Request URI does not have a port
-    #[error("Request URI does not have a port")]
-    MissingPort,
+    /// Server URL could not be parsed
+    #[error("Invalid server URL")]
+    InvalidServerUrl(#[source] url::ParseError),
+
+    /// Custom request header is malformed
+    #[error("Invalid header '{0}'")]
+    InvalidHeader(String),
+
+    /// Request body is not valid JSON
+    #[error("Request body is not valid JSON")]
+    InvalidRequestBody(#[source] serde_json::Error),
+
+    /// HTTP request could not be built
+    #[error("Failed to build request")]
+    RequestBuildFailed(#[source] hyper::http::Error),
 
     /// Failed to connect to notary server
-    #[error("Failed to connect to notary server: {0}")]
-    NotaryConnectionFailed(String),
+    #[error("Failed to connect to notary server")]
+    NotaryConnectionFailed(#[source] BoxError),
 
-    /// Failed to create presentation
-    #[error("Failed to create presentation: {0}")]
-    PresentationCreationFailed(String),
+    /// Failed to open a TCP connection to the server
+    #[error("Failed to connect to server {host}:{port}")]
+    ServerConnectionFailed {
+        host: String,
+        port: u16,
+        #[source]
+        source: io::Error,
+    },
 
-    /// Failed to notarize request
-    #[error("Failed to notarize request: {0}")]
-    NotarizationFailed(String),
+    /// Failed to set up the MPC-TLS prover with the notary
+    #[error("Failed to set up MPC-TLS with the notary")]
+    MpcSetupFailed(#[source] TlsnProverError),
 
-    /// Failed to serialize presentation
-    #[error("Failed to serialize presentation: {0}")]
-    SerializationFailed(String),
+    /// Server responded with a status other than 200 OK
+    #[error("Server responded with status: {0}")]
+    UnexpectedStatus(u16),
 
-    /// Failed to create tokio runtime
-    #[error("Failed to create tokio runtime: {0}")]
-    RuntimeCreationFailed(String),
+    /// Failed to create presentation
+    #[error("Failed to create presentation")]
+    PresentationCreationFailed(#[source] BoxError),
 
-    /// Failed to parse AST node
-    #[error("Failed to parse AST node: missing required element")]
-    AstParsingFailed,
+    /// Failed to notarize request
+    #[error("Failed to notarize request")]
+    NotarizationFailed(#[source] BoxError),
 
-    /// UTF-8 conversion error
-    #[error(transparent)]
-    Utf8ConversionError(#[from] std::string::FromUtf8Error),
+    /// None of the requested fields were found in the transcript
+    #[error("No data selected for disclosure was found in the transcript")]
+    NothingToDisclose,
 
-    /// Prove  builder error
-    #[error(transparent)]
-    ProveConfigBuilderError(#[from] ProveConfigBuilderError),
+    /// Failed to serialize presentation
+    #[error("Failed to serialize presentation")]
+    SerializationFailed(#[source] bincode::Error),
+
+    /// Failed to write an output file
+    #[error("Failed to write '{}'", path.display())]
+    WriteFailed {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
 
     /// Prover error from the TLSN library
     #[error(transparent)]
@@ -63,26 +226,216 @@ pub enum ProverError {
     #[error(transparent)]
     ProtocolConfigBuilderError(#[from] ProtocolConfigBuilderError),
 
-    /// IO error
-    #[error(transparent)]
-    IoError(#[from] io::Error),
-
     /// Hyper HTTP error
     #[error(transparent)]
     HyperError(#[from] HyperError),
+}
 
-    /// Pest parser error for request parsing
-    #[error(transparent)]
-    PestRequestError(#[from] PestError<RequestRule>),
+impl ProverError {
+    /// Stable machine-readable code identifying the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProverError::InvalidScheme => "INVALID_SCHEME",
+            ProverError::MissingAuthority => "MISSING_AUTHORITY",
+            ProverError::InvalidServerUrl(_) => "INVALID_SERVER_URL",
+            ProverError::InvalidHeader(_) => "INVALID_HEADER",
+            ProverError::InvalidRequestBody(_) => "INVALID_REQUEST_BODY",
+            ProverError::RequestBuildFailed(_) => "REQUEST_BUILD_FAILED",
+            ProverError::NotaryConnectionFailed(_) => "NOTARY_CONNECTION_FAILED",
+            ProverError::ServerConnectionFailed { .. } => "SERVER_CONNECTION_FAILED",
+            ProverError::MpcSetupFailed(_) => "MPC_SETUP_FAILED",
+            ProverError::UnexpectedStatus(_) => "UNEXPECTED_STATUS",
+            ProverError::PresentationCreationFailed(_) => "PRESENTATION_CREATION_FAILED",
+            ProverError::NotarizationFailed(_) => "NOTARIZATION_FAILED",
+            ProverError::NothingToDisclose => "NOTHING_TO_DISCLOSE",
+            ProverError::SerializationFailed(_) => "SERIALIZATION_FAILED",
+            ProverError::WriteFailed { .. } => "WRITE_FAILED",
+            ProverError::TlsnProverError(_) => "TLSN_PROVER_ERROR",
+            ProverError::ProtocolConfigBuilderError(_) => "PROTOCOL_CONFIG_INVALID",
+            ProverError::HyperError(_) => "HTTP_ERROR",
+        }
+    }
+}
 
-    /// Pest parser error for response parsing
-    #[error(transparent)]
-    PestResponseError(#[from] PestError<ResponseRule>),
+/// Errors while verifying a presentation
+#[derive(Error, Debug)]
+pub enum VerifierError {
+    /// Presentation bytes could not be read
+    #[error("Failed to read '{}'", path.display())]
+    ReadFailed {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// Presentation bytes are not a bincode-serialized presentation
+    #[error("Failed to deserialize presentation")]
+    DeserializationFailed(#[source] bincode::Error),
+
+    /// Signature, commitments or server identity did not verify
+    #[error("Presentation verification failed")]
+    VerificationFailed(#[source] PresentationError),
+
+    /// Prover did not reveal the server name
+    #[error("Prover did not reveal the server name")]
+    MissingServerName,
+
+    /// Prover did not reveal any transcript data
+    #[error("Prover did not reveal any transcript data")]
+    MissingTranscript,
+}
+
+impl VerifierError {
+    /// Stable machine-readable code identifying the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            VerifierError::ReadFailed { .. } => "PRESENTATION_READ_FAILED",
+            VerifierError::DeserializationFailed(_) => "PRESENTATION_DESERIALIZATION_FAILED",
+            VerifierError::VerificationFailed(_) => "PRESENTATION_VERIFICATION_FAILED",
+            VerifierError::MissingServerName => "MISSING_SERVER_NAME",
+            VerifierError::MissingTranscript => "MISSING_TRANSCRIPT",
+        }
+    }
+}
+
+/// Errors while parsing an HTTP request or response transcript
+#[derive(Error, Debug)]
+pub enum ParserError {
+    /// Transcript is not valid UTF-8
+    #[error("Transcript is not valid UTF-8")]
+    InvalidUtf8(#[from] std::string::FromUtf8Error),
+
+    /// Request does not match the request grammar
+    #[error("Failed to parse request")]
+    Request(#[source] Box<PestError<RequestRule>>),
+
+    /// Response does not match the response grammar, e.g. its body is not JSON
+    #[error("Failed to parse response")]
+    Response(#[source] Box<PestError<ResponseRule>>),
+
+    /// Selector does not match the selector grammar
+    #[error("Failed to parse selector")]
+    Selector(#[source] Box<PestError<SelectorRule>>),
+
+    /// Redacted transcript does not match the redacted grammar, e.g. a hidden run is
+    /// not a whole JSON value
+    #[error("Failed to parse redacted transcript")]
+    Redacted(#[source] Box<PestError<RedactedRule>>),
+
+    /// Selector matches the selector grammar but cannot be used, e.g. its array
+    /// index does not fit in 64 bits
+    #[error("Invalid selector: {0}")]
+    InvalidSelector(String),
+
+    /// Message body framing does not match its headers
+    #[error("Invalid message body: {0}")]
+    InvalidBody(String),
+
+    /// Body uses a content or transfer coding that cannot be disclosed selectively
+    #[error("Unsupported body encoding '{0}', only identity and chunked are supported")]
+    UnsupportedEncoding(String),
+
+    /// Part of a redacted transcript needed to parse it was not disclosed
+    #[error("Redacted transcript does not disclose the {0}")]
+    Undisclosed(&'static str),
+
+    /// Parse tree is missing a required element
+    #[error("Failed to parse AST node: {0}")]
+    MissingElement(&'static str),
+}
+
+impl ParserError {
+    /// Stable machine-readable code identifying the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserError::InvalidUtf8(_) => "UTF8_CONVERSION_FAILED",
+            ParserError::Request(_) => "REQUEST_PARSE_FAILED",
+            ParserError::Response(_) => "RESPONSE_PARSE_FAILED",
+            ParserError::Selector(_) => "SELECTOR_PARSE_FAILED",
+            ParserError::Redacted(_) => "REDACTED_PARSE_FAILED",
+            ParserError::InvalidSelector(_) => "INVALID_SELECTOR",
+            ParserError::InvalidBody(_) => "INVALID_BODY",
+            ParserError::UnsupportedEncoding(_) => "UNSUPPORTED_ENCODING",
+            ParserError::Undisclosed(_) => "UNDISCLOSED_DATA",
+            ParserError::MissingElement(_) => "AST_PARSING_FAILED",
+        }
+    }
+}
+
+impl From<PestError<RequestRule>> for ParserError {
+    fn from(e: PestError<RequestRule>) -> Self {
+        ParserError::Request(Box::new(e))
+    }
+}
+
+impl From<PestError<ResponseRule>> for ParserError {
+    fn from(e: PestError<ResponseRule>) -> Self {
+        ParserError::Response(Box::new(e))
+    }
+}
+
+impl From<PestError<SelectorRule>> for ParserError {
+    fn from(e: PestError<SelectorRule>) -> Self {
+        ParserError::Selector(Box::new(e))
+    }
+}
+
+impl From<PestError<RedactedRule>> for ParserError {
+    fn from(e: PestError<RedactedRule>) -> Self {
+        ParserError::Redacted(Box::new(e))
+    }
+}
+
+impl From<&'static str> for ParserError {
+    fn from(e: &'static str) -> Self {
+        ParserError::MissingElement(e)
+    }
+}
+
+/// Errors while building the Noir circuit witness from a presentation
+#[derive(Error, Debug)]
+pub enum WitnessError {
+    /// Presentation could not be mirrored into the witness structs
+    #[error("Failed to read presentation fields")]
+    Json(#[from] serde_json::Error),
+
+    /// Attestation field could not be BCS encoded
+    #[error("Failed to encode attestation field")]
+    Encoding(#[from] bcs::Error),
+
+    /// Notary key is not a valid secp256k1 key
+    #[error("Invalid notary verifying key")]
+    InvalidVerifyingKey(#[from] k256::ecdsa::Error),
+
+    /// Plaintext index lies outside the revealed transcript
+    #[error("Commitment index {index} is outside the revealed transcript (length {len})")]
+    IndexOutOfBounds { index: usize, len: usize },
+
+    /// Hash commitment in the attestation has no secret in the transcript proof
+    #[error("No hash secret for the {direction:?} commitment at position {position}")]
+    MissingHashSecret {
+        direction: Direction,
+        position: usize,
+    },
+
+    /// Hash secret in the transcript proof has no commitment in the attestation
+    #[error("No hash commitment for a {direction:?} hash secret")]
+    MissingHashCommitment { direction: Direction },
+}
 
-    /// Generic string error
-    #[error("{0}")]
-    StringError(String),
+impl WitnessError {
+    /// Stable machine-readable code identifying the kind of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            WitnessError::Json(_) => "WITNESS_JSON_FAILED",
+            WitnessError::Encoding(_) => "WITNESS_ENCODING_FAILED",
+            WitnessError::InvalidVerifyingKey(_) => "INVALID_VERIFYING_KEY",
+            WitnessError::IndexOutOfBounds { .. } => "INDEX_OUT_OF_BOUNDS",
+            WitnessError::MissingHashSecret { .. } => "MISSING_HASH_SECRET",
+            WitnessError::MissingHashCommitment { .. } => "MISSING_HASH_COMMITMENT",
+        }
+    }
 }
 
-// For backward compatibility with existing code
-pub type Result<T> = std::result::Result<T, ProverError>;
+/// Result of the operations spanning several stages, e.g. notarizing a request
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,33 @@
+//! Prover, verifier and witness building for TLSNotary presentations of
+//! Revolut API responses.
+
+mod api;
+pub mod ast;
+pub mod body;
+pub mod config;
+mod disclosure;
+pub mod errors;
+pub mod policy;
+pub mod prover;
+pub mod redacted;
+pub mod request;
+pub mod response;
+pub mod selector;
+pub mod trust;
+mod utils;
+pub mod verifier;
+pub mod witness;
+
+pub use api::{ProveOutput, prove};
+pub use ast::{HeaderPart, Headers, ObjectEntry, RangedHeader, RangedValue, Searchable};
+pub use config::{NotaryConfig, PROVER_CONFIG, ProverConfig};
+pub use errors::{ConfigError, Error, ParserError, ProverError, VerifierError, WitnessError};
+pub use policy::{DisclosurePolicy, HashAlgorithm, RequestLinePart};
+pub use prover::{NotarizedPresentation, ProverService};
+pub use redacted::{RedactedParser, parse_redacted_request_line, parse_redacted_response};
+pub use request::{Request, RequestParser, parse_request};
+pub use response::{Response, ResponseParser, parse_response};
+pub use selector::{DisclosureMode, FieldSelector, Match, Selector};
+pub use trust::{TrustArgs, TrustStore};
+pub use verifier::{RevolutTransaction, verify, verify_presentation};
+pub use witness::build_witness;
@@ -1,149 +1,201 @@
-use http_body_util::Empty;
-use hyper::{
-    Request,
-    body::Bytes,
-    header::{HeaderName, HeaderValue},
-};
-use url::Url;
-
-mod ast;
-mod config;
-mod errors;
-mod prover;
-mod request;
-mod response;
-mod utils;
-
-pub use config::PROVER_CONFIG;
-pub use errors::ProverError;
-pub use prover::ProverService;
-
+use clap::{Args, Parser, Subcommand};
 use macro_rules_attribute::apply;
 use smol_macros::main;
+use std::path::{Path, PathBuf};
+use tlsn_revolut::config::DEFAULT_NOTARY_REQUEST_TIMEOUT;
+use tlsn_revolut::{
+    ConfigError, DisclosurePolicy, Error, NotaryConfig, PROVER_CONFIG, ProverConfig, ProverError,
+    TrustArgs, TrustStore, prove,
+};
 
-#[apply(main!)]
-async fn main() {
-    prove(
-        "https://localhost:3001/api/retail/transaction/5".to_string(),
-        vec![
-            "user-agent: curl/8.4.0".to_string(),
-            "cookie: test=123".to_string(),
-            "x-device-id: 1234567890".to_string(),
-        ],
-        "http://localhost:7047".to_string(),
-    )
-    .await
-    .unwrap();
+/// Prover for TLSNotary presentations of HTTPS API responses
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 }
 
-#[derive(Debug, thiserror::Error)]
-pub enum ApiError {
-    /// Input is missing or invalid
-    #[error("Input is missing or invalid")]
-    MissingInput,
-    /// Index out of bounds error with details
-    #[error("Index {index} out of bounds (size: {size})")]
-    IndexOutOfBounds { index: u32, size: u32 },
-    /// Generic error with a message
-    #[error("{0}")]
-    Generic(String),
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Notarize a request and write the resulting presentation to disk
+    Prove(ProveArgs),
 }
 
-pub async fn prove(
+#[derive(Debug, Args)]
+struct ProveArgs {
+    /// URL of the server endpoint to notarize
     server_url: String,
+
+    /// HTTP method of the request
+    #[arg(short = 'X', long, default_value = "GET")]
+    method: String,
+
+    /// Request header in 'Key: Value' format, can be repeated
+    #[arg(short = 'H', long = "header", value_name = "HEADER")]
     headers: Vec<String>,
+
+    /// File with one 'Key: Value' header per line, can be repeated
+    #[arg(long = "header-file", value_name = "PATH")]
+    header_files: Vec<PathBuf>,
+
+    /// JSON request body
+    #[arg(short = 'd', long, conflicts_with = "data_file")]
+    data: Option<String>,
+
+    /// File containing the JSON request body
+    #[arg(long, value_name = "PATH")]
+    data_file: Option<PathBuf>,
+
+    /// URL of the notary server, TLS is used for https URLs
+    #[arg(short = 'n', long, default_value = "http://localhost:7047")]
     notary_url: String,
-) -> Result<Vec<u8>, ApiError> {
-    // Parse the URLs to extract host and port information
-    let notary_parsed = Url::parse(&notary_url)
-        .map_err(|e| ApiError::Generic(format!("Failed to parse notary URL: {}", e)))?;
-
-    let notary_host = notary_parsed
-        .host_str()
-        .ok_or_else(|| ApiError::Generic("No host found in notary URL".to_string()))?;
-
-    let notary_port = notary_parsed
-        .port()
-        .unwrap_or(if notary_parsed.scheme() == "https" {
-            443
-        } else {
-            80
-        });
-
-    // Parse the server URL to get host and port information for both HTTP request and TLS connection
-    let server_parsed = Url::parse(&server_url)
-        .map_err(|e| ApiError::Generic(format!("Failed to parse server URL: {}", e)))?;
-
-    let server_host = server_parsed
-        .host_str()
-        .ok_or_else(|| ApiError::Generic("No host found in server URL".to_string()))?;
-
-    let server_port = server_parsed
-        .port()
-        .unwrap_or(if server_parsed.scheme() == "https" {
-            443
-        } else {
-            80
-        });
-
-    // Build the HTTP request
-    let mut request = Request::builder()
-        .method("GET")
-        .uri(server_url.as_str())
-        .header("connection", "close")
-        .header("host", server_host.to_string())
-        .header("Content-Length", "0")
-        .body(Empty::<Bytes>::new())
-        .map_err(|e| ApiError::Generic(format!("Failed to build request: {}", e)))?;
-
-    // Add custom headers to the request
-    let request_headers = request.headers_mut();
-
-    for header in headers {
-        if let Some((key, value)) = header.split_once(':') {
-            let key = key
-                .trim()
-                .parse::<HeaderName>()
-                .map_err(|e| ApiError::Generic(format!("Invalid header name '{}': {}", key, e)))?;
-
-            let value = value.trim().parse::<HeaderValue>().map_err(|e| {
-                ApiError::Generic(format!("Invalid header value '{}': {}", value, e))
-            })?;
-
-            request_headers.insert(key, value);
-        } else {
-            return Err(ApiError::Generic(format!(
-                "Header '{}' is not in 'Key: Value' format",
-                header
-            )));
-        }
+
+    /// Additional PEM or DER root certificate to trust for the notary, can be repeated
+    #[arg(long = "notary-ca-cert", value_name = "PATH")]
+    notary_ca_certs: Vec<PathBuf>,
+
+    /// Trust only the root certificates in this PEM or DER file for the notary, instead of
+    /// the webpki roots. This limits which CA may issue the notary's certificate, it does
+    /// not pin the notary's own certificate
+    #[arg(long, value_name = "PATH", conflicts_with = "notary_ca_certs")]
+    notary_only_ca_cert: Option<PathBuf>,
+
+    /// API key for notaries that require authorization
+    #[arg(long, env = "NOTARY_API_KEY", hide_env_values = true)]
+    notary_api_key: Option<String>,
+
+    /// API path prefix of the notary, overrides the path of --notary-url
+    #[arg(long, value_name = "PREFIX")]
+    notary_path_prefix: Option<String>,
+
+    /// Seconds to wait for the notary to accept the session request
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_NOTARY_REQUEST_TIMEOUT)]
+    notary_timeout: usize,
+
+    /// Maximum bytes of data that can be sent
+    #[arg(long, default_value_t = PROVER_CONFIG.max_sent_data)]
+    max_sent_data: usize,
+
+    /// Maximum bytes of data that can be received
+    #[arg(long, default_value_t = PROVER_CONFIG.max_recv_data)]
+    max_recv_data: usize,
+
+    /// Where to write the serialized presentation
+    #[arg(short, long, default_value = "presentation.tlsn")]
+    output: PathBuf,
+
+    /// Disclosure policy JSON file, defaults to the Revolut transaction policy
+    #[arg(short, long, value_name = "PATH")]
+    policy: Option<PathBuf>,
+
+    #[command(flatten)]
+    trust: TrustArgs,
+}
+
+#[apply(main!)]
+async fn main() {
+    tracing_subscriber::fmt::init();
+
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Prove(args) => run_prove(args).await,
+    };
+
+    if let Err(e) = result {
+        eprintln!("Error [{}]: {}", e.code(), e.report());
+        std::process::exit(1);
+    }
+}
+
+async fn run_prove(args: ProveArgs) -> Result<(), Error> {
+    // Collect headers from files first so explicit `-H` flags take precedence
+    let mut headers = Vec::new();
+    for path in &args.header_files {
+        headers.extend(read_header_file(path)?);
+    }
+    headers.extend(args.headers);
+
+    let body = match args.data_file {
+        Some(path) => Some(
+            std::fs::read_to_string(&path)
+                .map_err(|source| ConfigError::ReadFailed { path, source })?,
+        ),
+        None => args.data,
+    };
+
+    let notary_trust_store = match args.notary_only_ca_cert {
+        Some(path) => TrustStore {
+            webpki_roots: false,
+            test_root_ca: false,
+            cert_files: vec![path],
+        },
+        None => TrustStore {
+            cert_files: args.notary_ca_certs,
+            ..TrustStore::webpki()
+        },
+    };
+    let mut notary = NotaryConfig::from_url(&args.notary_url)?
+        .with_trust_store(notary_trust_store)
+        .with_request_timeout(args.notary_timeout);
+    if let Some(api_key) = args.notary_api_key {
+        notary = notary.with_api_key(api_key);
+    }
+    if let Some(path_prefix) = args.notary_path_prefix {
+        notary = notary.with_path_prefix(path_prefix);
     }
 
-    println!("➡️ REQUEST:");
-    println!("{:?}", request);
-
-    // Create a prover service, notarize and create presentation
-    let prover = ProverService::new();
-    let presentation = prover
-        .notarize_and_create_presentation(
-            request,
-            PROVER_CONFIG.max_sent_data,
-            PROVER_CONFIG.max_recv_data,
-            notary_host,
-            notary_port,
-            server_host,
-            server_port,
-        )
-        .await
-        .map_err(|e| ApiError::Generic(format!("Prover encountered an error: {}", e)))?;
-
-    println!("Presentation created");
-
-    let serialized_presentation = bincode::serialize(&presentation)
-        .map_err(|e| ApiError::Generic(format!("Failed to serialize presentation: {}", e)))?;
+    let config = ProverConfig {
+        max_sent_data: args.max_sent_data,
+        max_recv_data: args.max_recv_data,
+        trust_store: args.trust.into(),
+    };
+
+    let policy = match args.policy {
+        Some(path) => DisclosurePolicy::from_file(path)?,
+        None => DisclosurePolicy::revolut_transaction(),
+    };
+
+    let output = prove(
+        args.server_url,
+        args.method,
+        headers,
+        body,
+        &notary,
+        policy,
+        &config,
+    )
+    .await?;
 
     // Write the presentation to disk.
-    std::fs::write("presentation.tlsn", serialized_presentation.clone()).unwrap();
+    std::fs::write(&args.output, output.presentation).map_err(|source| {
+        ProverError::WriteFailed {
+            path: args.output.clone(),
+            source,
+        }
+    })?;
+
+    println!(
+        "Presentation from notary session {} written to {}",
+        output.session_id,
+        args.output.display()
+    );
+
+    Ok(())
+}
 
-    Ok(serialized_presentation)
+/// Read `Key: Value` headers from a file, skipping blank lines and `#` comments
+fn read_header_file(path: &Path) -> Result<Vec<String>, ConfigError> {
+    let content = std::fs::read_to_string(path).map_err(|source| ConfigError::ReadFailed {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
 }
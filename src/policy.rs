@@ -0,0 +1,225 @@
+use crate::errors::ConfigError;
+use crate::selector::FieldSelector;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Request headers that carry credentials and are never revealed
+pub const CREDENTIAL_HEADERS: &[&str] = &[
+    "cookie",
+    "authorization",
+    "proxy-authorization",
+    "x-device-id",
+];
+
+/// Part of the HTTP request line that can be revealed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestLinePart {
+    /// Request method, e.g. `GET`
+    Method,
+    /// Request target, e.g. `/api/retail/transaction/5`
+    Url,
+    /// Protocol version, e.g. `HTTP/1.1`
+    Protocol,
+}
+
+/// Hash algorithm used for the per-field plaintext hash commitments
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    /// SHA-256
+    #[default]
+    Sha256,
+    /// BLAKE3
+    Blake3,
+}
+
+/// Declarative description of which request and response fields are disclosed
+///
+/// Loaded from a JSON file, for example:
+///
+/// ```json
+/// {
+///   "response_keypaths": ["comment", "currency", "amount", "recipient.username"],
+///   "response_selectors": [
+///     "$.legs[?(@.state==\"COMPLETED\")].amount",
+///     { "selector": "$.id", "mode": "value" }
+///   ],
+///   "response_headers": ["content-type"],
+///   "response_header_values": ["date"],
+///   "request_headers": ["host"],
+///   "request_line": ["method", "url"],
+///   "redacted_request_headers": ["x-api-key"],
+///   "reveal_structure": true,
+///   "hash_algorithm": "blake3"
+/// }
+/// ```
+///
+/// Missing fields take their value from `DisclosurePolicy::default()`, which reveals the
+/// request line and `host` header and nothing else. Headers in `CREDENTIAL_HEADERS` and
+/// `redacted_request_headers` are never revealed. Unless `reveal_structure` is off, the
+/// keys and brackets enclosing each revealed body field are revealed with it. Every
+/// revealed field gets its own plaintext hash commitment using `hash_algorithm`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisclosurePolicy {
+    /// Dot-separated keypaths in the response JSON body to reveal
+    pub response_keypaths: Vec<String>,
+    /// JSONPath-style selectors matching response JSON body values to reveal, each with
+    /// an optional disclosure mode
+    pub response_selectors: Vec<FieldSelector>,
+    /// Response header names whose whole line is revealed
+    pub response_headers: Vec<String>,
+    /// Response header names whose value is revealed without the name
+    pub response_header_values: Vec<String>,
+    /// Dot-separated keypaths in the request JSON body to reveal
+    pub request_keypaths: Vec<String>,
+    /// JSONPath-style selectors matching request JSON body values to reveal, each with
+    /// an optional disclosure mode
+    pub request_selectors: Vec<FieldSelector>,
+    /// Request header names whose whole line is revealed
+    pub request_headers: Vec<String>,
+    /// Request header names whose value is revealed without the name
+    pub request_header_values: Vec<String>,
+    /// Parts of the request line to reveal
+    pub request_line: Vec<RequestLinePart>,
+    /// Additional request header names that must stay hidden
+    pub redacted_request_headers: Vec<String>,
+    /// Also reveal the brackets, keys and separators enclosing each revealed body field,
+    /// the delimiters of the hidden values next to it, and the response framing, so the
+    /// verifier can confirm the path it was found at, see `RangedValue::structure_ranges`
+    pub reveal_structure: bool,
+    /// Hash algorithm for the per-field commitments
+    pub hash_algorithm: HashAlgorithm,
+}
+
+impl Default for DisclosurePolicy {
+    fn default() -> Self {
+        Self {
+            response_keypaths: Vec::new(),
+            response_selectors: Vec::new(),
+            response_headers: Vec::new(),
+            response_header_values: Vec::new(),
+            request_keypaths: Vec::new(),
+            request_selectors: Vec::new(),
+            request_headers: vec!["host".to_string()],
+            request_header_values: Vec::new(),
+            request_line: vec![
+                RequestLinePart::Method,
+                RequestLinePart::Url,
+                RequestLinePart::Protocol,
+            ],
+            redacted_request_headers: Vec::new(),
+            reveal_structure: true,
+            hash_algorithm: HashAlgorithm::default(),
+        }
+    }
+}
+
+impl DisclosurePolicy {
+    /// Policy for the Revolut transaction endpoint
+    pub fn revolut_transaction() -> Self {
+        Self {
+            response_keypaths: [
+                "state",
+                "comment",
+                "currency",
+                "amount",
+                "recipient.id",
+                "recipient.type",
+                "recipient.username",
+                "recipient.code",
+            ]
+            .map(String::from)
+            .to_vec(),
+            ..Default::default()
+        }
+    }
+
+    /// Parse a policy from its JSON representation
+    pub fn from_json(json: &str) -> Result<Self, ConfigError> {
+        let policy: Self = serde_json::from_str(json).map_err(ConfigError::PolicyParseFailed)?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    /// Load a policy from a JSON file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|source| ConfigError::ReadFailed {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// Response keypaths as string slices, ready for `Searchable`
+    pub fn response_keypaths(&self) -> Vec<&str> {
+        self.response_keypaths.iter().map(String::as_str).collect()
+    }
+
+    /// Response header names as string slices, ready for `Searchable`
+    pub fn response_headers(&self) -> Vec<&str> {
+        self.response_headers.iter().map(String::as_str).collect()
+    }
+
+    /// Response header names revealed by value only, ready for `Searchable`
+    pub fn response_header_values(&self) -> Vec<&str> {
+        self.response_header_values
+            .iter()
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Request keypaths as string slices, ready for `Searchable`
+    pub fn request_keypaths(&self) -> Vec<&str> {
+        self.request_keypaths.iter().map(String::as_str).collect()
+    }
+
+    /// Request header names as string slices, ready for `Searchable`
+    ///
+    /// Credential-bearing headers are filtered out even if listed.
+    pub fn request_headers(&self) -> Vec<&str> {
+        self.request_headers
+            .iter()
+            .map(String::as_str)
+            .filter(|name| !self.is_redacted_request_header(name))
+            .collect()
+    }
+
+    /// Request header names revealed by value only, ready for `Searchable`
+    ///
+    /// Credential-bearing headers are filtered out even if listed.
+    pub fn request_header_values(&self) -> Vec<&str> {
+        self.request_header_values
+            .iter()
+            .map(String::as_str)
+            .filter(|name| !self.is_redacted_request_header(name))
+            .collect()
+    }
+
+    /// Whether a request header must stay hidden regardless of what the policy reveals
+    pub fn is_redacted_request_header(&self, name: &str) -> bool {
+        CREDENTIAL_HEADERS
+            .iter()
+            .copied()
+            .chain(self.redacted_request_headers.iter().map(String::as_str))
+            .any(|redacted| redacted.eq_ignore_ascii_case(name))
+    }
+
+    /// Reject policies that ask to reveal a credential-bearing request header
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(name) = self
+            .request_headers
+            .iter()
+            .chain(&self.request_header_values)
+            .find(|name| self.is_redacted_request_header(name))
+        {
+            return Err(ConfigError::InvalidDisclosurePolicy(format!(
+                "request header '{}' carries credentials and cannot be revealed",
+                name
+            )));
+        }
+        Ok(())
+    }
+}
@@ -1,50 +1,81 @@
+use crate::config::{NotaryConfig, ProverConfig};
 use crate::errors::{ProverError, Result};
+use crate::policy::DisclosurePolicy;
 use crate::utils;
-use http_body_util::Empty;
+use http_body_util::Full;
 use hyper::{Request, body::Bytes};
+use std::path::Path;
 use tlsn_core::presentation::Presentation;
 
+/// Presentation produced by a notarization session
+#[derive(Debug)]
+pub struct NotarizedPresentation {
+    /// Session id assigned by the notary, for audit logs
+    pub session_id: String,
+    /// Presentation disclosing the fields selected by the policy
+    pub presentation: Presentation,
+}
+
 /// Service responsible for generating proofs
-#[derive(Debug, Default)]
-pub struct ProverService;
+#[derive(Debug)]
+pub struct ProverService {
+    /// Which request and response fields end up in the presentation
+    policy: DisclosurePolicy,
+}
+
+impl Default for ProverService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl ProverService {
-    /// Create a new ProverService instance
+    /// Create a new ProverService instance with the Revolut transaction policy
     pub fn new() -> Self {
-        Self {}
+        Self::with_policy(DisclosurePolicy::revolut_transaction())
+    }
+
+    /// Create a new ProverService instance with a custom disclosure policy
+    pub fn with_policy(policy: DisclosurePolicy) -> Self {
+        Self { policy }
+    }
+
+    /// Create a new ProverService instance with a disclosure policy loaded from a JSON file
+    pub fn from_policy_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::with_policy(DisclosurePolicy::from_file(path)?))
     }
 
     /// Create presentation from notarization and presentation creation
     pub async fn notarize_and_create_presentation(
         &self,
-        request: Request<Empty<Bytes>>,
-        max_sent_data: usize,
-        max_recv_data: usize,
-        notary_host: &str,
-        notary_port: u16,
+        request: Request<Full<Bytes>>,
+        config: &ProverConfig,
+        notary: &NotaryConfig,
         server_host: &str,
         server_port: u16,
-    ) -> Result<Presentation> {
+    ) -> Result<NotarizedPresentation> {
         // Validate the request
         if request.uri().scheme().map(|s| s.as_str()) != Some("https") {
-            return Err(ProverError::InvalidScheme);
+            return Err(ProverError::InvalidScheme.into());
         }
 
         // Notarize the request
-        let (attestation, secrets) = utils::notarize(
+        let (attestation, secrets, session_id) = utils::notarize(
             request,
-            notary_host,
-            notary_port,
-            max_sent_data,
-            max_recv_data,
+            notary,
+            config,
             server_host,
             server_port,
+            &self.policy,
         )
         .await?;
 
         // Create presentation from attestation and secrets
-        let presentation = utils::create_presentation(attestation, secrets).await?;
+        let presentation = utils::create_presentation(attestation, secrets, &self.policy).await?;
 
-        Ok(presentation)
+        Ok(NotarizedPresentation {
+            session_id,
+            presentation,
+        })
     }
 }
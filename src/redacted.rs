@@ -0,0 +1,328 @@
+use crate::ast::{CommonParser, CommonRule, CommonRuleType, Headers, RangedHeader, RangedValue};
+use crate::errors::ParserError;
+use crate::request::{self, RequestLine, RequestParser, parse_request_line};
+use crate::response::{Response, decode_body};
+use pest::Parser;
+use pest_derive::Parser;
+use std::ops::Range;
+
+/// Parser for redacted transcripts, extending the response grammar
+#[derive(Parser)]
+#[grammar = "response.pest"]
+#[grammar = "redacted.pest"]
+pub struct RedactedParser;
+
+/// Parse a redacted HTTP response transcript from a presentation.
+///
+/// `data` is the received transcript with every undisclosed byte set to zero and
+/// `authed` the ranges of it that were disclosed. The status line, the end of the
+/// header section and the body framing must be disclosed. Only header lines that are
+/// disclosed as a whole are kept, and every JSON value whose bytes are not all
+/// disclosed is returned as `RangedValue::Redacted`, and so is every value under an
+/// undisclosed key. Hidden values are expected to be marked by their delimiters, see
+/// `RangedValue::structure_ranges`, and every value that follows an unmarked one in
+/// any of its enclosing containers is redacted too.
+pub fn parse_redacted_response(
+    data: &[u8],
+    authed: &[Range<usize>],
+) -> Result<Response, ParserError> {
+    let input = String::from_utf8(data.to_vec())?;
+
+    let mut status_line = None;
+    let mut headers = Headers::default();
+    let mut body = None;
+
+    for pair in RedactedParser::parse(Rule::redacted_response, &input)? {
+        match pair.as_rule() {
+            Rule::preamble => {
+                let range = pair.as_span().start()..pair.as_span().end();
+                if !is_authed(authed, &range) {
+                    return Err(ParserError::Undisclosed("status line"));
+                }
+                status_line = Some(RangedHeader {
+                    range: range.clone(),
+                    value_range: range,
+                    _value: pair.as_str().to_string(),
+                });
+            }
+            Rule::header => {
+                let (name, header) = CommonParser::parse_header(pair)?;
+                if is_authed(authed, &header.range) {
+                    headers.push(name, header);
+                }
+            }
+            Rule::body => {
+                body = Some(pair);
+            }
+            _ => continue,
+        }
+    }
+
+    let body = body.ok_or("Missing body")?;
+    let (decoded, body_map) = decode_body(&headers, body.as_str(), body.as_span().start())?;
+
+    let json = RedactedParser::parse(Rule::redacted_body, &decoded)?
+        .next()
+        .ok_or("Missing JSON body")?;
+    let mut content = CommonParser::parse_value(json);
+    redact_undisclosed(&mut content, &|range| {
+        body_map
+            .raw_ranges(range)
+            .iter()
+            .all(|raw| is_authed(authed, raw))
+    });
+
+    Ok(Response {
+        status_line: status_line.ok_or("Missing status line")?,
+        headers,
+        content,
+        body: body_map,
+    })
+}
+
+/// Parse the request line of a redacted HTTP request transcript from a presentation.
+///
+/// The whole request line, including its line ending, must be disclosed.
+pub fn parse_redacted_request_line(
+    data: &[u8],
+    authed: &[Range<usize>],
+) -> Result<RequestLine, ParserError> {
+    let end = data
+        .windows(2)
+        .position(|w| w == b"\r\n")
+        .map(|i| i + 2)
+        .filter(|&end| is_authed(authed, &(0..end)))
+        .ok_or(ParserError::Undisclosed("request line"))?;
+    let line = String::from_utf8(data[..end].to_vec())?;
+
+    let pair = RequestParser::parse(request::Rule::request_line, &line)?
+        .next()
+        .ok_or("Missing request line")?;
+    Ok(parse_request_line(pair)?)
+}
+
+/// Replace every value that is not fully disclosed with `RangedValue::Redacted`.
+///
+/// A hidden value is delimited when the bytes marking its extent were disclosed: the
+/// quotes of a string, the brackets of an object or array, or the first byte of a
+/// number, boolean or `null`. Its hidden bytes are taken to be that one value, so the
+/// members after it keep their place. A bare run of hidden bytes is not delimited and
+/// may hide any amount of structure: it may have swallowed the start of a nested
+/// object and turned that object's members into siblings. Every value after it in its
+/// container is therefore redacted too, and so is everything after the container in
+/// its parents. Delimiters are trusted to belong to the value they enclose, which the
+/// redacted transcript alone cannot show.
+///
+/// Returns whether the extent of the value can be relied on.
+fn redact_undisclosed(value: &mut RangedValue, authed: &impl Fn(Range<usize>) -> bool) -> bool {
+    match &mut *value {
+        RangedValue::Object { value: entries, .. } => redact_members(
+            entries.iter_mut().map(|entry| {
+                let key_disclosed = authed(entry.key_range.clone());
+                (key_disclosed, &mut entry.value)
+            }),
+            authed,
+        ),
+        RangedValue::Array { value: items, .. } => {
+            redact_members(items.iter_mut().map(|item| (true, item)), authed)
+        }
+        // Marked hidden values start with a disclosed byte, bare runs do not
+        RangedValue::Redacted { range } => authed(range.start..range.start + 1),
+        _ => {
+            let delimited = value.delimiter_ranges().into_iter().all(authed);
+            if !authed(value.get_range()) {
+                redact(value);
+            }
+            delimited
+        }
+    }
+}
+
+/// Redact the members of a container in document order, each with whether its key
+/// is disclosed, redacting every member after the first one that is not delimited
+fn redact_members<'a>(
+    members: impl Iterator<Item = (bool, &'a mut RangedValue)>,
+    authed: &impl Fn(Range<usize>) -> bool,
+) -> bool {
+    let mut delimited = true;
+    for (key_disclosed, value) in members {
+        if !delimited {
+            redact(value);
+            continue;
+        }
+        delimited = redact_undisclosed(value, authed);
+        // A value cannot be attributed to a key that is not disclosed
+        if !key_disclosed {
+            redact(value);
+        }
+    }
+    delimited
+}
+
+/// Replace a value with `RangedValue::Redacted`
+fn redact(value: &mut RangedValue) {
+    *value = RangedValue::Redacted {
+        range: value.get_range(),
+    };
+}
+
+/// Whether every byte of `range` lies in one of the authenticated ranges
+fn is_authed(authed: &[Range<usize>], range: &Range<usize>) -> bool {
+    let mut pos = range.start;
+    while pos < range.end {
+        match authed.iter().find(|r| r.start <= pos && pos < r.end) {
+            Some(r) => pos = r.end,
+            None => return false,
+        }
+    }
+    true
+}
+
+impl CommonRule for Rule {
+    fn rule_type(&self) -> CommonRuleType {
+        match self {
+            Rule::redacted_object => CommonRuleType::Object,
+            Rule::redacted_array => CommonRuleType::Array,
+            Rule::string => CommonRuleType::String,
+            Rule::number => CommonRuleType::Number,
+            Rule::boolean => CommonRuleType::Boolean,
+            Rule::null => CommonRuleType::Null,
+            Rule::hidden_container | Rule::hidden_scalar | Rule::hidden => CommonRuleType::Redacted,
+            _ => CommonRuleType::Other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Response transcript for `body` with the given parts of the body hidden, and
+    /// the ranges of it that are disclosed
+    fn redacted_response(body: &str, hidden: &[&str]) -> (Vec<u8>, Vec<Range<usize>>) {
+        let input = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let body_start = input.len() - body.len();
+        let mut data = input.into_bytes();
+        for part in hidden {
+            let start = body_start + body.find(part).unwrap();
+            data[start..start + part.len()].fill(0);
+        }
+
+        // Everything that was not hidden is disclosed
+        let mut authed: Vec<Range<usize>> = Vec::new();
+        for (i, _) in data.iter().enumerate().filter(|(_, byte)| **byte != 0) {
+            match authed.last_mut() {
+                Some(range) if range.end == i => range.end += 1,
+                _ => authed.push(i..i + 1),
+            }
+        }
+        (data, authed)
+    }
+
+    #[test]
+    fn disclosed_values_are_kept() {
+        let (data, authed) = redacted_response(r#"{"id":"t1","amount":100,"x":[1,2]}"#, &[]);
+        let response = parse_redacted_response(&data, &authed).unwrap();
+
+        assert_eq!(
+            response.content.get_keypath("id").unwrap().as_str(),
+            Some("t1")
+        );
+        assert_eq!(
+            response.content.get_keypath("amount").unwrap().as_i64(),
+            Some(100)
+        );
+        assert!(matches!(
+            response.content.get_keypath("x"),
+            Some(RangedValue::Array { .. })
+        ));
+    }
+
+    #[test]
+    fn values_after_a_bare_hidden_run_are_redacted() {
+        let body = r#"{"id":"t1","fee":{"b":7,"c":2},"amount":100}"#;
+        let (data, authed) = redacted_response(body, &["7"]);
+        let response = parse_redacted_response(&data, &authed).unwrap();
+
+        assert_eq!(
+            response.content.get_keypath("id").unwrap().as_str(),
+            Some("t1")
+        );
+        assert!(matches!(
+            response.content.get_keypath("fee.b"),
+            Some(RangedValue::Redacted { .. })
+        ));
+        assert!(matches!(
+            response.content.get_keypath("fee.c"),
+            Some(RangedValue::Redacted { .. })
+        ));
+        assert!(matches!(
+            response.content.get_keypath("amount"),
+            Some(RangedValue::Redacted { .. })
+        ));
+    }
+
+    #[test]
+    fn values_after_a_delimited_hidden_value_are_kept() {
+        let body =
+            r#"{"id":"t1","legId":"l1","balance":1234,"fee":{"a":1},"state":"DONE","amount":100}"#;
+        let (data, authed) =
+            redacted_response(body, &["id", "t1", "legId", "l1", "234", r#""a":1"#]);
+        let response = parse_redacted_response(&data, &authed).unwrap();
+
+        assert_eq!(
+            response.content.get_keypath("state").unwrap().as_str(),
+            Some("DONE")
+        );
+        assert_eq!(
+            response.content.get_keypath("amount").unwrap().as_i64(),
+            Some(100)
+        );
+        assert!(matches!(
+            response.content.get_keypath("balance"),
+            Some(RangedValue::Redacted { .. })
+        ));
+        assert!(matches!(
+            response.content.get_keypath("fee"),
+            Some(RangedValue::Redacted { .. })
+        ));
+    }
+
+    #[test]
+    fn values_under_a_hidden_key_are_redacted() {
+        let body = r#"{"amount":100,"state":"DONE"}"#;
+        let (data, authed) = redacted_response(body, &["amount"]);
+        let response = parse_redacted_response(&data, &authed).unwrap();
+
+        let RangedValue::Object { value: entries, .. } = &response.content else {
+            panic!("expected an object");
+        };
+        assert!(matches!(entries[0].value, RangedValue::Redacted { .. }));
+        assert!(response.content.get_keypath("amount").is_none());
+        assert_eq!(
+            response.content.get_keypath("state").unwrap().as_str(),
+            Some("DONE")
+        );
+    }
+
+    #[test]
+    fn nested_members_cannot_be_moved_to_the_top_level() {
+        let body = r#"{"id":"t1","amount":100,"fee":{"b":1,"amount":5,"c":2},"x":1}"#;
+        let (data, authed) = redacted_response(body, &[r#""t1","amount":100,"fee":{"b":1"#, "2}"]);
+        let response = parse_redacted_response(&data, &authed).unwrap();
+
+        for keypath in ["id", "amount", "c", "x"] {
+            assert!(
+                matches!(
+                    response.content.get_keypath(keypath),
+                    Some(RangedValue::Redacted { .. })
+                ),
+                "{keypath} should be redacted"
+            );
+        }
+    }
+}
@@ -1,17 +1,29 @@
-use crate::ast::{CommonParser, CommonRule, CommonRuleType, RangedHeader, RangedValue, Searchable};
+use crate::ast::{
+    CommonParser, CommonRule, CommonRuleType, Headers, RangedHeader, RangedValue, Searchable,
+};
+use crate::errors::ParserError;
+use crate::policy::RequestLinePart;
 use pest::iterators::Pair;
 use pest::{Parser, iterators::Pairs};
 use pest_derive::Parser;
-use std::{collections::HashMap, ops::Range};
+use std::ops::Range;
 
 #[derive(Parser)]
 #[grammar = "request.pest"]
 pub struct RequestParser;
 
+#[derive(Debug)]
+pub struct RequestLine {
+    pub range: Range<usize>,
+    pub method: RangedHeader,
+    pub url: RangedHeader,
+    pub protocol: RangedHeader,
+}
+
 #[derive(Debug)]
 pub struct Request {
-    pub request_line: RangedHeader,
-    pub headers: HashMap<String, RangedHeader>,
+    pub request_line: RequestLine,
+    pub headers: Headers,
     pub content: Option<RangedValue>,
 }
 
@@ -20,23 +32,19 @@ impl TryFrom<Pairs<'_, Rule>> for Request {
 
     fn try_from(pairs: Pairs<Rule>) -> Result<Self, Self::Error> {
         let mut request_line = None;
-        let mut headers = HashMap::new();
+        let mut headers = Headers::default();
         let mut content = RangedValue::default();
 
         for pair in pairs {
             match pair.as_rule() {
                 Rule::request_line => {
-                    let range = pair.as_span().start()..pair.as_span().end();
-                    request_line = Some(RangedHeader {
-                        range,
-                        _value: pair.as_str().to_string(),
-                    });
+                    request_line = Some(parse_request_line(pair)?);
                 }
                 Rule::header => {
                     let header = parse_request_header(pair)?;
-                    headers.insert(header.0, header.1);
+                    headers.push(header.0, header.1);
                 }
-                Rule::object | Rule::array => {
+                rule if rule.rule_type() != CommonRuleType::Other => {
                     content = parse_request_value(pair);
                 }
                 _ => continue,
@@ -51,10 +59,27 @@ impl TryFrom<Pairs<'_, Rule>> for Request {
     }
 }
 
-pub fn _parse_request(input: &str) -> Result<Request, &'static str> {
-    let pairs =
-        RequestParser::parse(Rule::request, input).map_err(|_| "Failed to parse request")?;
-    Request::try_from(pairs)
+/// Parse a raw HTTP request transcript.
+pub fn parse_request(input: &str) -> Result<Request, ParserError> {
+    let pairs = RequestParser::parse(Rule::request, input)?;
+    Ok(Request::try_from(pairs)?)
+}
+
+/// Parses a `request_line` rule into its method, url and protocol parts.
+pub fn parse_request_line(pair: Pair<Rule>) -> Result<RequestLine, &'static str> {
+    let range = pair.as_span().start()..pair.as_span().end();
+    let mut inner = pair.into_inner().map(|p| RangedHeader {
+        range: p.as_span().start()..p.as_span().end(),
+        value_range: p.as_span().start()..p.as_span().end(),
+        _value: p.as_str().to_string(),
+    });
+
+    Ok(RequestLine {
+        range,
+        method: inner.next().ok_or("Missing method in request line")?,
+        url: inner.next().ok_or("Missing url in request line")?,
+        protocol: inner.next().ok_or("Missing protocol in request line")?,
+    })
 }
 
 /// Parses a `header` rule and returns its key-value pair with range for request.
@@ -67,6 +92,34 @@ pub fn parse_request_value(pair: Pair<Rule>) -> RangedValue {
     CommonParser::parse_value(pair)
 }
 
+impl Request {
+    /// Get the ranges of the requested request line parts.
+    pub fn get_request_line_ranges(&self, parts: &[RequestLinePart]) -> Vec<Range<usize>> {
+        let line = &self.request_line;
+
+        // Reveal the whole line, separators included, when every part is requested
+        if [
+            RequestLinePart::Method,
+            RequestLinePart::Url,
+            RequestLinePart::Protocol,
+        ]
+        .iter()
+        .all(|part| parts.contains(part))
+        {
+            return vec![line.range.clone()];
+        }
+
+        parts
+            .iter()
+            .map(|part| match part {
+                RequestLinePart::Method => line.method.range.clone(),
+                RequestLinePart::Url => line.url.range.clone(),
+                RequestLinePart::Protocol => line.protocol.range.clone(),
+            })
+            .collect()
+    }
+}
+
 impl CommonRule for Rule {
     fn rule_type(&self) -> CommonRuleType {
         match self {
@@ -82,7 +135,7 @@ impl CommonRule for Rule {
 }
 
 impl Searchable for Request {
-    fn get_headers(&self) -> &HashMap<String, RangedHeader> {
+    fn get_headers(&self) -> &Headers {
         &self.headers
     }
 
@@ -94,3 +147,86 @@ impl Searchable for Request {
         vec![]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// POST request transcript with the given body
+    fn with_body(body: &str) -> String {
+        format!(
+            "POST /api/transfer HTTP/1.1\r\nhost: example.com\r\ncontent-length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    #[test]
+    fn request_without_body() {
+        let request = parse_request("GET /api HTTP/1.1\r\nhost: example.com\r\n\r\n").unwrap();
+        assert_eq!(request.request_line.url._value, "/api");
+        assert_eq!(request.headers.len(), 1);
+    }
+
+    #[test]
+    fn request_targets() {
+        for target in [
+            "/api/retail/transaction/7a2f-01",
+            "/a/b;v=1,2/(x)!*'$+?q=a+b&r=%20&s=[0]",
+            "https://[2001:db8::1]:8443/path?x=y",
+            "example.com:443",
+            "*",
+        ] {
+            let request = parse_request(&format!("GET {target} HTTP/1.1\r\n\r\n")).unwrap();
+            assert_eq!(request.request_line.url._value, target);
+        }
+
+        for target in ["/a#fragment", "/a b", "/a\"b", "/a<b>"] {
+            assert!(
+                parse_request(&format!("GET {target} HTTP/1.1\r\n\r\n")).is_err(),
+                "target {target:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn any_json_text_is_a_body() {
+        for (body, value) in [
+            (r#"{"amount":100}"#, r#"{"amount":100}"#),
+            ("[1, 2]", "[1, 2]"),
+            ("42", "42"),
+            (r#""x""#, r#""x""#),
+            ("true", "true"),
+            ("null", "null"),
+            (" \r\n\t{\"a\":1}\n ", "{\"a\":1}"),
+            ("\n42\n", "42"),
+        ] {
+            let input = with_body(body);
+            let request = parse_request(&input).unwrap();
+            let range = request.get_content().unwrap().get_range();
+            assert_eq!(&input[range], value, "body {body:?}");
+        }
+    }
+
+    #[test]
+    fn bodies_accepted_by_serde_json_parse() {
+        for body in [
+            r#"{"a":[1,-2.5e3,"é\n"],"b":{"c":null,"d":false}}"#,
+            " [] ",
+            "0",
+            "-0.5",
+            r#""\"quoted\"""#,
+            "{\n  \"pretty\": true\n}\n",
+        ] {
+            assert!(serde_json::from_str::<serde_json::Value>(body).is_ok());
+            assert!(parse_request(&with_body(body)).is_ok(), "body {body:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_bodies_are_rejected() {
+        for body in [r#"{"a":}"#, "42 43", "{} {}", "[1,]", "nul", "'x'"] {
+            assert!(parse_request(&with_body(body)).is_err(), "body {body:?}");
+        }
+    }
+}
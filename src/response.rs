@@ -1,8 +1,13 @@
-use crate::ast::{CommonParser, CommonRule, CommonRuleType, RangedHeader, RangedValue, Searchable};
+use crate::ast::{
+    CommonParser, CommonRule, CommonRuleType, HeaderPart, Headers, RangedHeader, RangedValue,
+    Searchable,
+};
+use crate::body::BodyMap;
+use crate::errors::ParserError;
 use pest::iterators::Pair;
 use pest::{Parser, iterators::Pairs};
 use pest_derive::Parser;
-use std::collections::HashMap;
+use std::ops::Range;
 
 #[derive(Parser)]
 #[grammar = "response.pest"]
@@ -10,40 +15,183 @@ pub struct ResponseParser;
 
 #[derive(Debug)]
 pub struct Response {
-    pub headers: HashMap<String, RangedHeader>,
+    pub status_line: RangedHeader,
+    pub headers: Headers,
+    /// Parsed JSON body, with ranges into the decoded body
     pub content: RangedValue,
+    /// Mapping from decoded body offsets to transcript offsets
+    pub body: BodyMap,
 }
 
 impl TryFrom<Pairs<'_, Rule>> for Response {
-    type Error = &'static str;
+    type Error = ParserError;
 
     fn try_from(pairs: Pairs<Rule>) -> Result<Self, Self::Error> {
-        let mut headers = HashMap::new();
-        let mut content = RangedValue::default();
+        let mut status_line = None;
+        let mut headers = Headers::default();
+        let mut body = None;
 
         for pair in pairs {
             match pair.as_rule() {
+                Rule::preamble => {
+                    let range = pair.as_span().start()..pair.as_span().end();
+                    status_line = Some(RangedHeader {
+                        range: range.clone(),
+                        value_range: range,
+                        _value: pair.as_str().to_string(),
+                    });
+                }
                 Rule::header => {
                     let header = parse_response_header(pair)?;
-                    headers.insert(header.0, header.1);
+                    headers.push(header.0, header.1);
                 }
-                Rule::object | Rule::array => {
-                    content = parse_response_value(pair);
+                Rule::body => {
+                    body = Some(pair);
                 }
                 _ => continue,
             }
         }
 
-        Ok(Self { headers, content })
+        let body = body.ok_or("Missing body")?;
+        let (decoded, body_map) = decode_body(&headers, body.as_str(), body.as_span().start())?;
+
+        // JSON tokens may straddle chunk boundaries, so parse the decoded body
+        let json = ResponseParser::parse(Rule::json_body, &decoded)?
+            .next()
+            .ok_or("Missing JSON body")?;
+        let content = parse_response_value(json);
+
+        Ok(Self {
+            status_line: status_line.ok_or("Missing status line")?,
+            headers,
+            content,
+            body: body_map,
+        })
     }
 }
 
-pub fn _parse_response(input: &str) -> Result<Response, &'static str> {
-    let pairs =
-        ResponseParser::parse(Rule::response, input).map_err(|_| "Failed to parse response")?;
+impl Response {
+    /// Get the status code from the status line, e.g. `200`
+    pub fn status_code(&self) -> Option<u16> {
+        self.status_line
+            ._value
+            .split_whitespace()
+            .nth(1)?
+            .parse()
+            .ok()
+    }
+
+    /// Get the ranges a verifier needs to locate and decode the body of a redacted transcript.
+    ///
+    /// These are the line ending of every header, the empty line ending the header section,
+    /// the `Transfer-Encoding` and `Content-Length` headers, any chunk framing and the
+    /// whitespace around the JSON body.
+    pub fn get_framing_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = self
+            .headers
+            .iter()
+            .map(|(_, header)| header.value_range.end..header.range.end)
+            .collect();
+
+        let header_end = self
+            .headers
+            .iter()
+            .last()
+            .map_or(self.status_line.range.end, |(_, header)| header.range.end);
+        ranges.push(header_end..self.body.raw_span().start);
+
+        ranges.extend(
+            self.get_header_ranges(&["transfer-encoding", "content-length"], HeaderPart::Line),
+        );
+        ranges.extend(self.body.framing_ranges());
+
+        let content = self.content.get_range();
+        ranges.extend(self.body.raw_ranges(0..content.start));
+        ranges.extend(self.body.raw_ranges(content.end..self.body.len()));
+
+        ranges.retain(|range| !range.is_empty());
+        ranges
+    }
+}
+
+/// Parse a raw HTTP response transcript.
+pub fn parse_response(input: &str) -> Result<Response, ParserError> {
+    let pairs = ResponseParser::parse(Rule::response, input)?;
     Response::try_from(pairs)
 }
 
+/// Check that the body of a raw HTTP response transcript can be decoded, looking only
+/// at its header section.
+///
+/// A compressed body is rarely valid UTF-8, so this runs before the transcript is
+/// converted to a string, which would otherwise fail with `InvalidUtf8` instead of
+/// `UnsupportedEncoding`.
+pub fn check_body_encoding(data: &[u8]) -> Result<(), ParserError> {
+    // Invalid bytes after the header section do not affect the headers
+    let input = String::from_utf8_lossy(data);
+
+    let mut headers = Headers::default();
+    for pair in ResponseParser::parse(Rule::response_head, &input)? {
+        if pair.as_rule() == Rule::header {
+            let (name, header) = parse_response_header(pair)?;
+            headers.push(name, header);
+        }
+    }
+    is_chunked(&headers)?;
+    Ok(())
+}
+
+/// Decode a response body according to its framing headers.
+///
+/// `raw` is everything after the header section, starting at `raw_start` in the transcript.
+pub(crate) fn decode_body(
+    headers: &Headers,
+    raw: &str,
+    raw_start: usize,
+) -> Result<(String, BodyMap), ParserError> {
+    let chunked = is_chunked(headers)?;
+    let content_length = headers
+        .get("content-length")
+        .map(|header| {
+            header._value.trim().parse::<usize>().map_err(|_| {
+                ParserError::InvalidBody(format!("invalid Content-Length '{}'", header._value))
+            })
+        })
+        .transpose()?;
+
+    let (decoded, body_map) = BodyMap::decode(raw.as_bytes(), raw_start, chunked, content_length)?;
+    Ok((String::from_utf8(decoded)?, body_map))
+}
+
+/// Whether the body is chunked, rejecting content and transfer codings other than
+/// `identity` and `chunked`
+fn is_chunked(headers: &Headers) -> Result<bool, ParserError> {
+    // Compressed bytes cannot be parsed or revealed field by field
+    if let Some(coding) = headers
+        .get_all("content-encoding")
+        .flat_map(|header| header._value.split(','))
+        .map(str::trim)
+        .find(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity"))
+    {
+        return Err(ParserError::UnsupportedEncoding(coding.to_string()));
+    }
+
+    // Transfer-Encoding takes precedence over Content-Length, RFC 9112 section 6.3
+    let mut chunked = false;
+    for coding in headers
+        .get_all("transfer-encoding")
+        .flat_map(|header| header._value.split(','))
+        .map(str::trim)
+        .filter(|coding| !coding.is_empty())
+    {
+        if !coding.eq_ignore_ascii_case("chunked") {
+            return Err(ParserError::UnsupportedEncoding(coding.to_string()));
+        }
+        chunked = true;
+    }
+    Ok(chunked)
+}
+
 /// Parses a `header` rule and returns its key-value pair with range.
 pub fn parse_response_header(pair: Pair<Rule>) -> Result<(String, RangedHeader), &'static str> {
     CommonParser::parse_header(pair)
@@ -69,11 +217,146 @@ impl CommonRule for Rule {
 }
 
 impl Searchable for Response {
-    fn get_headers(&self) -> &HashMap<String, RangedHeader> {
+    fn get_headers(&self) -> &Headers {
         &self.headers
     }
 
     fn get_content(&self) -> Option<&RangedValue> {
         Some(&self.content)
     }
+
+    fn get_additional_ranges(&self) -> Vec<Range<usize>> {
+        // Always reveal the status line so the verifier can see the request succeeded
+        vec![self.status_line.range.clone()]
+    }
+
+    fn to_transcript_ranges(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        // Skip the chunk framing between the body bytes
+        self.body.raw_ranges(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Response with the given status line and header lines and an empty JSON body
+    fn parse(status_line: &str, header_lines: &[&str]) -> Result<Response, ParserError> {
+        let mut input = format!("{status_line}\r\n");
+        for line in header_lines {
+            input.push_str(line);
+            input.push_str("\r\n");
+        }
+        input.push_str("Content-Length: 2\r\n\r\n{}");
+        parse_response(&input)
+    }
+
+    /// Value of the first header with the given name
+    fn value<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+        response
+            .headers
+            .get(name)
+            .map(|header| header._value.as_str())
+    }
+
+    #[test]
+    fn content_security_policy() {
+        let csp = "default-src 'self'; script-src 'self' https://cdn.example.com \
+                   'nonce-r4nd0m+/=='; img-src * data: blob:; frame-ancestors 'none'; \
+                   report-uri /csp-report?source=web&v=2#top";
+        let response = parse(
+            "HTTP/1.1 200 OK",
+            &[&format!("Content-Security-Policy: {csp}")],
+        )
+        .unwrap();
+
+        assert_eq!(value(&response, "content-security-policy"), Some(csp));
+    }
+
+    #[test]
+    fn multiple_set_cookie_headers() {
+        let response = parse(
+            "HTTP/1.1 200 OK",
+            &[
+                "set-cookie: session=a1b2c3; Path=/; Secure; HttpOnly; SameSite=Lax",
+                "Set-Cookie: prefs=\"{theme:dark}\"; Expires=Wed, 21 Oct 2026 07:28:00 GMT",
+                "set-cookie: tracking=; Max-Age=0",
+            ],
+        )
+        .unwrap();
+
+        let cookies: Vec<&str> = response
+            .headers
+            .get_all("set-cookie")
+            .map(|header| header._value.as_str())
+            .collect();
+        assert_eq!(
+            cookies,
+            [
+                "session=a1b2c3; Path=/; Secure; HttpOnly; SameSite=Lax",
+                "prefs=\"{theme:dark}\"; Expires=Wed, 21 Oct 2026 07:28:00 GMT",
+                "tracking=; Max-Age=0",
+            ]
+        );
+    }
+
+    #[test]
+    fn link_header() {
+        let link = "<https://api.example.com/items?page=2&per_page=50>; rel=\"next\", \
+                    <https://api.example.com/items?page=9>; rel=\"last\"; title*=UTF-8''%E2%82%AC";
+        let response = parse("HTTP/1.1 200 OK", &[&format!("link: {link}")]).unwrap();
+
+        assert_eq!(value(&response, "Link"), Some(link));
+    }
+
+    #[test]
+    fn non_ascii_values() {
+        let response = parse(
+            "HTTP/1.1 200 OK",
+            &["x-user-name: Zoë Müller-Łukasz 🙂", "x-city: 東京"],
+        )
+        .unwrap();
+
+        assert_eq!(
+            value(&response, "x-user-name"),
+            Some("Zoë Müller-Łukasz 🙂")
+        );
+        assert_eq!(value(&response, "x-city"), Some("東京"));
+
+        // The value range is in bytes, not characters
+        let header = response.headers.get("x-city").unwrap();
+        assert_eq!(header.value_range.len(), "東京".len());
+    }
+
+    #[test]
+    fn empty_and_tab_separated_values() {
+        let response = parse(
+            "HTTP/1.1 200 OK",
+            &[
+                "x-empty:",
+                "x-blank:  \t ",
+                "x-tabs:\tfirst\tsecond \t third\t",
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(value(&response, "x-empty"), Some(""));
+        assert_eq!(value(&response, "x-blank"), Some(""));
+        // Leading and trailing whitespace is not part of the value, inner whitespace is
+        assert_eq!(value(&response, "x-tabs"), Some("first\tsecond \t third"));
+    }
+
+    #[test]
+    fn status_line_reason_phrase() {
+        let response = parse("HTTP/1.1 204", &[]).unwrap();
+        assert_eq!(response.status_code(), Some(204));
+
+        let response = parse("HTTP/1.1 404 Not Found", &[]).unwrap();
+        assert_eq!(response.status_code(), Some(404));
+
+        let response = parse("HTTP/1.1 200 ", &[]).unwrap();
+        assert_eq!(response.status_code(), Some(200));
+
+        assert!(parse("HTTP/1.1 2000 OK", &[]).is_err());
+    }
 }
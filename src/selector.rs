@@ -0,0 +1,616 @@
+use crate::ast::{ObjectEntry, RangedValue, decode_json_string};
+use crate::errors::ParserError;
+use pest::Parser;
+use pest::iterators::Pair;
+use pest_derive::Parser;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Parser)]
+#[grammar = "selector.pest"]
+pub struct SelectorParser;
+
+/// JSONPath-style selector evaluated against a `RangedValue` tree
+///
+/// Supports child names (`$.legs`, `$['legs']`), array indices counted from the
+/// start or the end (`[0]`, `[-1]`), wildcards (`[*]`, `.*`), descendants
+/// (`$..username`) and filters on the children of a value, e.g.
+/// `$.legs[?(@.state=="COMPLETED")].amount`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Selector {
+    text: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// Apply the step to the current values
+    Child(Step),
+    /// Apply the step to the current values and all their descendants
+    Descendant(Step),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Filter(Condition),
+}
+
+/// Filter test on a value relative to each child, `@`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Condition {
+    /// Names and indices leading from the child to the tested value
+    path: Vec<Step>,
+    /// Comparison with a literal, or `None` to test that the value exists
+    test: Option<(Comparison, Literal)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Literal {
+    String(String),
+    Number(String),
+    Bool(bool),
+    Null,
+}
+
+/// Part of a matched value that is revealed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisclosureMode {
+    /// Only the value bytes, e.g. `100`
+    Value,
+    /// The key bound to its value, e.g. `"amount":100`
+    #[default]
+    Member,
+    /// The whole object or array containing the value
+    Object,
+    /// Only the key and separator, e.g. `"amount":`, proving the key is present
+    Key,
+}
+
+/// Selector together with how its matches are revealed
+///
+/// Deserializes from a bare selector string, which uses `DisclosureMode::Member`, or
+/// from `{"selector": "$.amount", "mode": "value"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "FieldSelectorRepr")]
+pub struct FieldSelector {
+    pub selector: Selector,
+    pub mode: DisclosureMode,
+}
+
+#[derive(Deserialize)]
+#[serde(
+    untagged,
+    expecting = "a selector string or an object with `selector` and optional `mode`"
+)]
+enum FieldSelectorRepr {
+    Selector(Selector),
+    WithMode {
+        selector: Selector,
+        #[serde(default)]
+        mode: DisclosureMode,
+    },
+}
+
+impl From<FieldSelectorRepr> for FieldSelector {
+    fn from(repr: FieldSelectorRepr) -> Self {
+        match repr {
+            FieldSelectorRepr::Selector(selector) => selector.into(),
+            FieldSelectorRepr::WithMode { selector, mode } => Self { selector, mode },
+        }
+    }
+}
+
+impl From<Selector> for FieldSelector {
+    fn from(selector: Selector) -> Self {
+        Self {
+            selector,
+            mode: DisclosureMode::default(),
+        }
+    }
+}
+
+/// Value matched by a selector
+#[derive(Debug, Clone, Copy)]
+pub struct Match<'a> {
+    pub value: &'a RangedValue,
+    /// Object member holding the value, `None` for array elements and the root
+    pub entry: Option<&'a ObjectEntry>,
+    /// Object or array containing the value, `None` for the root
+    pub parent: Option<&'a RangedValue>,
+}
+
+impl Match<'_> {
+    /// Range revealing the matched value in the given mode
+    ///
+    /// Array elements and the root have no key, so `Member` reveals their value and
+    /// `Key` reveals nothing. `Object` on the root reveals the root itself.
+    pub fn range(&self, mode: DisclosureMode) -> Option<Range<usize>> {
+        match mode {
+            DisclosureMode::Value => Some(self.value.get_range()),
+            DisclosureMode::Member => Some(match self.entry {
+                Some(entry) => entry.range.clone(),
+                None => self.value.get_range(),
+            }),
+            DisclosureMode::Object => Some(self.parent.unwrap_or(self.value).get_range()),
+            DisclosureMode::Key => self
+                .entry
+                .map(|entry| entry.key_range.start..entry.colon + 1),
+        }
+    }
+}
+
+impl Selector {
+    /// Parse a selector such as `$.legs[0].amount`.
+    pub fn parse(text: &str) -> Result<Self, ParserError> {
+        let segments = SelectorParser::parse(Rule::selector, text)?
+            .filter(|pair| pair.as_rule() != Rule::EOI)
+            .map(parse_segment)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            text: text.to_string(),
+            segments,
+        })
+    }
+
+    /// The selector as written
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Every value in `root` matched by this selector, in document order
+    pub fn select<'a>(&self, root: &'a RangedValue) -> Vec<Match<'a>> {
+        let mut nodes = vec![Match {
+            value: root,
+            entry: None,
+            parent: None,
+        }];
+
+        for segment in &self.segments {
+            nodes = match segment {
+                Segment::Child(step) => nodes
+                    .iter()
+                    .flat_map(|node| step.apply(node.value))
+                    .collect(),
+                Segment::Descendant(step) => {
+                    let mut visited = Vec::new();
+                    for node in nodes {
+                        collect_descendants(node, &mut visited);
+                    }
+                    visited
+                        .iter()
+                        .flat_map(|node| step.apply(node.value))
+                        .collect()
+                }
+            };
+        }
+
+        nodes
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl TryFrom<String> for Selector {
+    type Error = ParserError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        Self::parse(&text)
+    }
+}
+
+impl From<Selector> for String {
+    fn from(selector: Selector) -> Self {
+        selector.text
+    }
+}
+
+impl Step {
+    fn apply<'a>(&self, value: &'a RangedValue) -> Vec<Match<'a>> {
+        match self {
+            Step::Name(name) => children(value)
+                .into_iter()
+                .filter(|child| child.entry.is_some_and(|entry| &entry.key == name))
+                .collect(),
+            Step::Wildcard => children(value),
+            Step::Index(index) => element(value, *index)
+                .map(|element| Match {
+                    value: element,
+                    entry: None,
+                    parent: Some(value),
+                })
+                .into_iter()
+                .collect(),
+            Step::Filter(condition) => children(value)
+                .into_iter()
+                .filter(|child| condition.matches(child.value))
+                .collect(),
+        }
+    }
+}
+
+impl Condition {
+    fn matches(&self, value: &RangedValue) -> bool {
+        let mut current = value;
+        for step in &self.path {
+            let next = match step {
+                Step::Name(name) => children(current)
+                    .into_iter()
+                    .find(|child| child.entry.is_some_and(|entry| &entry.key == name))
+                    .map(|child| child.value),
+                Step::Index(index) => element(current, *index),
+                Step::Wildcard | Step::Filter(_) => None,
+            };
+            match next {
+                Some(next) => current = next,
+                None => return false,
+            }
+        }
+
+        let Some((comparison, literal)) = &self.test else {
+            return true;
+        };
+
+        let ordering = compare(current, literal);
+        match comparison {
+            Comparison::Eq => ordering == Some(Ordering::Equal),
+            Comparison::Ne => ordering != Some(Ordering::Equal),
+            Comparison::Lt => ordering == Some(Ordering::Less),
+            Comparison::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            Comparison::Gt => ordering == Some(Ordering::Greater),
+            Comparison::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+        }
+    }
+}
+
+/// Members of an object or elements of an array
+fn children(parent: &RangedValue) -> Vec<Match<'_>> {
+    match parent {
+        RangedValue::Object { value, .. } => value
+            .iter()
+            .map(|entry| Match {
+                value: &entry.value,
+                entry: Some(entry),
+                parent: Some(parent),
+            })
+            .collect(),
+        RangedValue::Array { value, .. } => value
+            .iter()
+            .map(|value| Match {
+                value,
+                entry: None,
+                parent: Some(parent),
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Array element at an index, negative indices count from the end
+fn element(value: &RangedValue, index: i64) -> Option<&RangedValue> {
+    let RangedValue::Array { value, .. } = value else {
+        return None;
+    };
+    let index = if index < 0 {
+        value.len().checked_sub(index.unsigned_abs() as usize)?
+    } else {
+        index as usize
+    };
+    value.get(index)
+}
+
+/// The node followed by all values nested in it, in document order
+fn collect_descendants<'a>(node: Match<'a>, visited: &mut Vec<Match<'a>>) {
+    visited.push(node);
+    for child in children(node.value) {
+        collect_descendants(child, visited);
+    }
+}
+
+/// Order a value against a literal, `None` when they are not comparable
+///
+/// Only strings and numbers are ordered; booleans and null only compare equal.
+fn compare(value: &RangedValue, literal: &Literal) -> Option<Ordering> {
+    match (value, literal) {
+        (RangedValue::String { value, .. }, Literal::String(literal)) => {
+            Some(value.as_str().cmp(literal))
+        }
+        (RangedValue::Number { lexeme, .. }, Literal::Number(literal)) => {
+            // Compare integers exactly and fall back to floating point otherwise
+            match (lexeme.parse::<i128>(), literal.parse::<i128>()) {
+                (Ok(a), Ok(b)) => Some(a.cmp(&b)),
+                _ => lexeme
+                    .parse::<f64>()
+                    .ok()?
+                    .partial_cmp(&literal.parse::<f64>().ok()?),
+            }
+        }
+        (RangedValue::Bool { _value, .. }, Literal::Bool(literal)) => {
+            (_value == literal).then_some(Ordering::Equal)
+        }
+        (RangedValue::Null { .. }, Literal::Null) => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+fn parse_segment(pair: Pair<Rule>) -> Result<Segment, ParserError> {
+    let rule = pair.as_rule();
+    let step = parse_step(
+        pair.into_inner()
+            .next()
+            .ok_or("Missing step in selector segment")?,
+    )?;
+
+    Ok(match rule {
+        Rule::descendant => Segment::Descendant(step),
+        _ => Segment::Child(step),
+    })
+}
+
+fn parse_step(pair: Pair<Rule>) -> Result<Step, ParserError> {
+    Ok(match pair.as_rule() {
+        Rule::name => Step::Name(pair.as_str().to_string()),
+        Rule::quoted_name => Step::Name(parse_quoted(pair)),
+        Rule::wildcard => Step::Wildcard,
+        Rule::index => Step::Index(pair.as_str().parse().map_err(|_| {
+            ParserError::InvalidSelector(format!(
+                "array index {} does not fit in 64 bits",
+                pair.as_str()
+            ))
+        })?),
+        Rule::filter => Step::Filter(parse_condition(
+            pair.into_inner()
+                .next()
+                .ok_or("Missing condition in filter")?,
+        )?),
+        _ => return Err(ParserError::MissingElement("Unexpected rule in selector")),
+    })
+}
+
+fn parse_condition(pair: Pair<Rule>) -> Result<Condition, ParserError> {
+    let mut inner = pair.into_inner();
+
+    let path = inner
+        .next()
+        .ok_or("Missing path in filter")?
+        .into_inner()
+        .map(parse_step)
+        .collect::<Result<_, _>>()?;
+
+    let test = match (inner.next(), inner.next()) {
+        (Some(comparison), Some(literal)) => {
+            let comparison = match comparison.as_str() {
+                "==" => Comparison::Eq,
+                "!=" => Comparison::Ne,
+                "<" => Comparison::Lt,
+                "<=" => Comparison::Le,
+                ">" => Comparison::Gt,
+                _ => Comparison::Ge,
+            };
+            let literal = match literal.as_rule() {
+                Rule::quoted_name => Literal::String(parse_quoted(literal)),
+                Rule::number => Literal::Number(literal.as_str().to_string()),
+                Rule::boolean => Literal::Bool(literal.as_str() == "true"),
+                _ => Literal::Null,
+            };
+            Some((comparison, literal))
+        }
+        _ => None,
+    };
+
+    Ok(Condition { path, test })
+}
+
+/// Decode a single or double quoted name or string literal
+fn parse_quoted(pair: Pair<Rule>) -> String {
+    let raw = pair
+        .into_inner()
+        .next()
+        .map(|inner| inner.as_str())
+        .unwrap_or_default();
+    decode_json_string(&raw.replace("\\'", "'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::{ResponseParser, Rule as ResponseRule, parse_response_value};
+
+    const JSON: &str = r#"{
+        "id": "t1",
+        "state": "COMPLETED",
+        "amount": -1050,
+        "legs": [
+            {"state": "COMPLETED", "amount": 100, "fee": {"amount": 5}},
+            {"state": "DECLINED", "amount": 18446744073709551616, "fee": null},
+            {"state": "COMPLETED", "amount": 2.5, "flagged": true}
+        ],
+        "recipient": {"user name": "al'ice", "tags": ["a", "b"]}
+    }"#;
+
+    fn parse_json(json: &str) -> RangedValue {
+        let pair = ResponseParser::parse(ResponseRule::json_body, json)
+            .unwrap()
+            .next()
+            .unwrap();
+        parse_response_value(pair)
+    }
+
+    /// Text of every value matched by `selector` in `JSON`
+    fn select(selector: &str) -> Vec<&'static str> {
+        let root = parse_json(JSON);
+        Selector::parse(selector)
+            .unwrap()
+            .select(&root)
+            .iter()
+            .map(|m| &JSON[m.value.get_range()])
+            .collect()
+    }
+
+    #[test]
+    fn parse_valid_selectors() {
+        for text in [
+            "$",
+            "$.id",
+            "$['id']",
+            "$[\"user name\"]",
+            "$.legs[0].amount",
+            "$.legs[-1]",
+            "$.legs[*].amount",
+            "$.legs.*",
+            "$..amount",
+            "$..[0]",
+            "$.legs[?(@.state==\"COMPLETED\")].amount",
+            "$.legs[?(@.fee.amount >= 5)]",
+            "$.legs[?@.flagged]",
+            "$.legs[ ?( @['state'] != 'DECLINED' ) ]",
+        ] {
+            let selector = Selector::parse(text).unwrap();
+            assert_eq!(selector.as_str(), text);
+            assert_eq!(selector.to_string(), text);
+        }
+    }
+
+    #[test]
+    fn parse_invalid_selectors() {
+        for text in [
+            "",
+            "id",
+            "$.",
+            "$..",
+            "$[",
+            "$[id]",
+            "$.legs[0",
+            "$.legs[1.5]",
+            "$.legs[?(@.state=COMPLETED)]",
+            "$.legs[?(state==\"COMPLETED\")]",
+            "$.id extra",
+        ] {
+            assert!(
+                matches!(Selector::parse(text), Err(ParserError::Selector(_))),
+                "{text} should not parse"
+            );
+        }
+
+        assert!(matches!(
+            Selector::parse("$.legs[99999999999999999999]"),
+            Err(ParserError::InvalidSelector(_))
+        ));
+    }
+
+    #[test]
+    fn deserialize_field_selectors() {
+        let selectors: Vec<FieldSelector> =
+            serde_json::from_str(r#"["$.id", {"selector": "$.amount", "mode": "value"}]"#).unwrap();
+        assert_eq!(selectors[0].selector.as_str(), "$.id");
+        assert_eq!(selectors[0].mode, DisclosureMode::Member);
+        assert_eq!(selectors[1].selector.as_str(), "$.amount");
+        assert_eq!(selectors[1].mode, DisclosureMode::Value);
+
+        assert!(serde_json::from_str::<FieldSelector>(r#""$.""#).is_err());
+        assert!(serde_json::from_str::<FieldSelector>(r#"{"mode": "value"}"#).is_err());
+    }
+
+    #[test]
+    fn select_names_and_indices() {
+        assert_eq!(select("$.id"), [r#""t1""#]);
+        assert_eq!(select("$['id']"), [r#""t1""#]);
+        assert_eq!(select("$.recipient['user name']"), [r#""al'ice""#]);
+        assert_eq!(select("$.legs[0].amount"), ["100"]);
+        assert_eq!(select("$.legs[-1].amount"), ["2.5"]);
+        assert_eq!(select("$.recipient.tags[1]"), [r#""b""#]);
+        assert!(select("$.missing").is_empty());
+        assert!(select("$.legs[3]").is_empty());
+        assert!(select("$.legs[-4]").is_empty());
+        assert!(select("$.id[0]").is_empty());
+        assert_eq!(select("$"), [JSON.trim()]);
+    }
+
+    #[test]
+    fn select_wildcards_and_descendants() {
+        assert_eq!(
+            select("$.legs[*].amount"),
+            ["100", "18446744073709551616", "2.5"]
+        );
+        assert_eq!(select("$.recipient.*").len(), 2);
+        // Descendants are visited in document order, the root included
+        assert_eq!(
+            select("$..amount"),
+            ["-1050", "100", "5", "18446744073709551616", "2.5"]
+        );
+        assert_eq!(select("$..tags[0]"), [r#""a""#]);
+    }
+
+    #[test]
+    fn select_with_filters() {
+        assert_eq!(
+            select(r#"$.legs[?(@.state=="COMPLETED")].amount"#),
+            ["100", "2.5"]
+        );
+        assert_eq!(
+            select("$.legs[?(@.state!='COMPLETED')].amount"),
+            ["18446744073709551616"]
+        );
+        assert_eq!(select("$.legs[?(@.amount > 100)].state"), [r#""DECLINED""#]);
+        // Integers are compared exactly, not through f64
+        assert!(select("$.legs[?(@.amount == 18446744073709551617)]").is_empty());
+        assert_eq!(
+            select("$.legs[?(@.amount <= 2.5)].state"),
+            [r#""COMPLETED""#]
+        );
+        assert_eq!(select("$.legs[?(@.fee.amount == 5)].amount"), ["100"]);
+        assert_eq!(
+            select("$.legs[?(@.fee == null)].amount"),
+            ["18446744073709551616"]
+        );
+        assert_eq!(select("$.legs[?(@.flagged == true)].amount"), ["2.5"]);
+        assert_eq!(select("$.legs[?(@.flagged)].amount"), ["2.5"]);
+        // Values of different types never compare equal
+        assert!(select(r#"$.legs[?(@.amount == "100")]"#).is_empty());
+    }
+
+    #[test]
+    fn match_ranges_by_mode() {
+        let json = r#"{"amount": 100, "legs": [7]}"#;
+        let root = parse_json(json);
+        let text = |selector: &str, mode| {
+            let matches = Selector::parse(selector).unwrap().select(&root);
+            matches[0].range(mode).map(|range| &json[range])
+        };
+
+        assert_eq!(text("$.amount", DisclosureMode::Value), Some("100"));
+        assert_eq!(
+            text("$.amount", DisclosureMode::Member),
+            Some(r#""amount": 100"#)
+        );
+        assert_eq!(text("$.amount", DisclosureMode::Key), Some(r#""amount":"#));
+        assert_eq!(text("$.amount", DisclosureMode::Object), Some(json));
+
+        // Array elements have no key
+        assert_eq!(text("$.legs[0]", DisclosureMode::Member), Some("7"));
+        assert_eq!(text("$.legs[0]", DisclosureMode::Key), None);
+        assert_eq!(text("$.legs[0]", DisclosureMode::Object), Some("[7]"));
+        assert_eq!(text("$", DisclosureMode::Object), Some(json));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::errors::ConfigError;
+use clap::Args;
+use futures_rustls::{pki_types::CertificateDer, rustls};
+use std::path::PathBuf;
+use tls_core::anchors::{OwnedTrustAnchor, RootCertStore};
+use tls_core::key::Certificate;
+use tls_core::verify::WebPkiVerifier;
+use tlsn_core::CryptoProvider;
+
+/// Root CA of the local test server fixture
+const TEST_ROOT_CA: &[u8] = include_bytes!("../certs/rootCA.der");
+
+/// Root certificates trusted when verifying the server's TLS certificate
+///
+/// Used by the prover during the TLS handshake and by the verifier when
+/// checking the server identity in a presentation. The prover also uses a
+/// trust store for its connection to the notary server. Certificates in it are
+/// trust anchors, so listing only one CA limits who may issue the notary's
+/// certificate but does not pin the certificate itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustStore {
+    /// Trust the Mozilla root certificates bundled with `webpki-roots`
+    pub webpki_roots: bool,
+    /// Trust the local test root CA from `certs/rootCA.der`
+    pub test_root_ca: bool,
+    /// Additional PEM or DER encoded root certificate files
+    pub cert_files: Vec<PathBuf>,
+}
+
+/// Command line flags selecting the root certificates of a `TrustStore`
+#[derive(Debug, Clone, Args)]
+pub struct TrustArgs {
+    /// Do not trust the webpki default root certificates
+    #[arg(long)]
+    pub no_webpki_roots: bool,
+
+    /// Trust the local test root CA, combine with --no-webpki-roots for test-only mode
+    #[arg(long)]
+    pub test_root_ca: bool,
+
+    /// Additional PEM or DER root certificate file to trust, can be repeated
+    #[arg(long = "ca-cert", value_name = "PATH")]
+    pub ca_certs: Vec<PathBuf>,
+}
+
+impl From<TrustArgs> for TrustStore {
+    fn from(args: TrustArgs) -> Self {
+        TrustStore {
+            webpki_roots: !args.no_webpki_roots,
+            test_root_ca: args.test_root_ca,
+            cert_files: args.ca_certs,
+        }
+    }
+}
+
+impl Default for TrustStore {
+    fn default() -> Self {
+        Self::webpki()
+    }
+}
+
+impl TrustStore {
+    /// Trust the webpki default roots only
+    pub const fn webpki() -> Self {
+        Self {
+            webpki_roots: true,
+            test_root_ca: false,
+            cert_files: Vec::new(),
+        }
+    }
+
+    /// Trust the local test root CA only, for offline testing with the server fixture
+    pub const fn test_only() -> Self {
+        Self {
+            webpki_roots: false,
+            test_root_ca: true,
+            cert_files: Vec::new(),
+        }
+    }
+
+    /// Additionally trust the root certificates in a PEM or DER file
+    pub fn with_cert_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cert_files.push(path.into());
+        self
+    }
+
+    /// Build the root certificate store for the MPC-TLS connection to the server
+    pub fn root_store(&self) -> Result<RootCertStore, ConfigError> {
+        let mut root_store = RootCertStore::empty();
+
+        if self.webpki_roots {
+            root_store.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+                OwnedTrustAnchor::from_subject_spki_name_constraints(
+                    ta.subject.to_vec(),
+                    ta.subject_public_key_info.to_vec(),
+                    ta.name_constraints.as_ref().map(|nc| nc.to_vec()),
+                )
+            }));
+        }
+
+        for (source, der) in self.certificates()? {
+            root_store
+                .add(&Certificate(der))
+                .map_err(|e| ConfigError::InvalidCertificate {
+                    origin: source.clone(),
+                    source: e.into(),
+                })?;
+        }
+
+        if root_store.is_empty() {
+            return Err(ConfigError::EmptyTrustStore);
+        }
+
+        Ok(root_store)
+    }
+
+    /// Build the root certificate store for the TLS connection to the notary server
+    pub fn rustls_root_store(&self) -> Result<rustls::RootCertStore, ConfigError> {
+        let mut root_store = rustls::RootCertStore::empty();
+
+        if self.webpki_roots {
+            root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+
+        for (source, der) in self.certificates()? {
+            root_store.add(CertificateDer::from(der)).map_err(|e| {
+                ConfigError::InvalidCertificate {
+                    origin: source.clone(),
+                    source: e.into(),
+                }
+            })?;
+        }
+
+        if root_store.is_empty() {
+            return Err(ConfigError::EmptyTrustStore);
+        }
+
+        Ok(root_store)
+    }
+
+    /// Build a crypto provider that verifies server certificates against this trust store
+    pub fn crypto_provider(&self) -> Result<CryptoProvider, ConfigError> {
+        Ok(CryptoProvider {
+            cert: WebPkiVerifier::new(self.root_store()?, None),
+            ..Default::default()
+        })
+    }
+
+    /// DER encoded certificates from the test root CA and the certificate files,
+    /// each paired with a description of where it came from
+    fn certificates(&self) -> Result<Vec<(String, Vec<u8>)>, ConfigError> {
+        let mut certs = Vec::new();
+
+        if self.test_root_ca {
+            certs.push(("test root CA".to_string(), TEST_ROOT_CA.to_vec()));
+        }
+
+        for path in &self.cert_files {
+            let bytes = std::fs::read(path).map_err(|source| ConfigError::ReadFailed {
+                path: path.clone(),
+                source,
+            })?;
+            let source = path.display().to_string();
+            for der in decode_certificates(&bytes, &source)? {
+                certs.push((source.clone(), der));
+            }
+        }
+
+        Ok(certs)
+    }
+}
+
+/// Decode every certificate in a PEM bundle, or treat the bytes as a single DER certificate
+fn decode_certificates(bytes: &[u8], source: &str) -> Result<Vec<Vec<u8>>, ConfigError> {
+    if !bytes.starts_with(b"-----BEGIN") {
+        return Ok(vec![bytes.to_vec()]);
+    }
+
+    let certs: Vec<Vec<u8>> = pem::parse_many(bytes)
+        .map_err(|e| ConfigError::InvalidCertificate {
+            origin: source.to_string(),
+            source: e.into(),
+        })?
+        .into_iter()
+        .filter(|pem| pem.tag() == "CERTIFICATE")
+        .map(|pem| pem.into_contents())
+        .collect();
+
+    if certs.is_empty() {
+        return Err(ConfigError::NoCertificates(source.to_string()));
+    }
+
+    Ok(certs)
+}
@@ -1,20 +1,18 @@
 use std::io::Read;
 use std::ops::Range;
 
-use pest::Parser;
-use tls_core::verify::WebPkiVerifier;
 use tlsn_core::Secrets;
 use tlsn_core::hash::HashAlgId;
 use tlsn_core::transcript::{Direction, TranscriptCommitmentKind};
 use tlsn_prover::Prover;
 use tlsn_prover::state::Committed;
 
-use crate::ast::Searchable;
-use crate::errors::{ProverError, Result};
-use crate::request::{Request, RequestParser, Rule as RequestRule};
-use crate::response::{Response, ResponseParser, Rule as ResponseRule};
+use crate::config;
+use crate::disclosure;
+use crate::errors::{ConfigError, ParserError, ProverError, Result};
+use crate::policy::{DisclosurePolicy, HashAlgorithm};
 
-use http_body_util::Empty;
+use http_body_util::Full;
 use hyper::{Request as HttpRequest, StatusCode, body::Bytes};
 use notary_client::{Accepted, NotarizationRequest, NotaryClient};
 use smol::net::TcpStream;
@@ -25,6 +23,7 @@ use tlsn_core::{
     transcript::TranscriptCommitConfig,
 };
 use tlsn_prover::{Prover as TlsProver, ProverConfig};
+use tracing::{debug, info};
 
 /// Trait for types that provide access to transcript data
 pub trait TranscriptProvider {
@@ -52,210 +51,226 @@ impl TranscriptProvider for Secrets {
     }
 }
 
-/// Notarize a request and return attestation and secrets
+/// Commitment kind used for every revealed field of the transcript
+fn commitment_kind(alg: HashAlgorithm) -> TranscriptCommitmentKind {
+    let alg = match alg {
+        HashAlgorithm::Sha256 => HashAlgId::SHA256,
+        HashAlgorithm::Blake3 => HashAlgId::BLAKE3,
+    };
+    TranscriptCommitmentKind::Hash { alg }
+}
+
+/// Notarize a request and return attestation, secrets and the notary session id
 pub async fn notarize(
-    request: HttpRequest<Empty<Bytes>>,
-    notary_host: &str,
-    notary_port: u16,
-    max_sent_data: usize,
-    max_recv_data: usize,
+    request: HttpRequest<Full<Bytes>>,
+    notary: &config::NotaryConfig,
+    config: &config::ProverConfig,
     server_host: &str,
     server_port: u16,
-) -> Result<(Attestation, Secrets)> {
+    policy: &DisclosurePolicy,
+) -> Result<(Attestation, Secrets, String)> {
     // Build a client to connect to the notary server.
-    let notary_client = NotaryClient::builder()
-        .host(notary_host)
-        .port(notary_port)
-        // WARNING: Always use TLS to connect to notary server, except if notary is running locally
-        // e.g. this example, hence `enable_tls` is set to False (else it always defaults to True).
-        .enable_tls(false)
+    let mut notary_builder = NotaryClient::builder();
+    notary_builder
+        .host(notary.host.as_str())
+        .port(notary.port)
+        .path_prefix(notary.path_prefix.as_str())
+        // TLS is only disabled for a notary running locally, see `NotaryConfig::from_url`.
+        .enable_tls(notary.tls)
+        .root_cert_store(notary.trust_store.rustls_root_store()?)
+        .request_timeout(notary.request_timeout);
+
+    if let Some(api_key) = &notary.api_key {
+        notary_builder.api_key(api_key.clone());
+    }
+
+    let notary_client = notary_builder
         .build()
-        .map_err(|e| ProverError::NotaryConnectionFailed(e.to_string()))?;
+        .map_err(|e| ConfigError::InvalidNotaryClient(e.into()))?;
 
-    println!("Notary client built");
+    debug!("Notary client built");
 
     let notarization_request = NotarizationRequest::builder()
-        .max_sent_data(max_sent_data)
-        .max_recv_data(max_recv_data)
+        .max_sent_data(config.max_sent_data)
+        .max_recv_data(config.max_recv_data)
         .build()
-        .map_err(|e| ProverError::NotarizationFailed(e.to_string()))?;
+        .map_err(|e| ConfigError::InvalidNotarizationRequest(e.into()))?;
 
-    println!("Notarization request built");
+    debug!("Notarization request built");
 
     let Accepted {
         io: notary_connection,
-        id: _session_id,
+        id: session_id,
         ..
     } = notary_client
         .request_notarization(notarization_request)
         .await
-        .expect("Could not connect to notary. Make sure it is running.");
-
-    println!("Notarization request sent");
+        .map_err(|e| ProverError::NotaryConnectionFailed(e.into()))?;
 
-    let mut root_store = tls_core::anchors::RootCertStore::empty();
-    root_store
-        .add(&tls_core::key::Certificate(
-            include_bytes!("../certs/rootCA.der").to_vec(),
-        ))
-        .map_err(|e| {
-            ProverError::NotarizationFailed(format!("Failed to add certificate: {}", e))
-        })?;
+    info!(%session_id, "Notarization session accepted");
 
-    let crypto_provider = CryptoProvider {
-        cert: WebPkiVerifier::new(root_store, None),
-        ..Default::default()
-    };
+    let crypto_provider = config.trust_store.crypto_provider()?;
 
     let prover_config = ProverConfig::builder()
         .server_name(server_host)
         .protocol_config(
             ProtocolConfig::builder()
-                .max_sent_data(max_sent_data)
-                .max_recv_data(max_recv_data)
-                .build()?,
+                .max_sent_data(config.max_sent_data)
+                .max_recv_data(config.max_recv_data)
+                .build()
+                .map_err(ProverError::ProtocolConfigBuilderError)?,
         )
         .crypto_provider(crypto_provider)
         .build()
-        .map_err(|e| ProverError::NotarizationFailed(e.to_string()))?;
+        .map_err(|e| ProverError::NotarizationFailed(e.into()))?;
 
-    println!("Prover config set");
+    debug!("Prover config set");
     let prover = TlsProver::new(prover_config)
         .setup(notary_connection)
-        .await?;
+        .await
+        .map_err(ProverError::MpcSetupFailed)?;
 
-    let client_socket = TcpStream::connect((server_host, server_port)).await?;
+    let client_socket = TcpStream::connect((server_host, server_port))
+        .await
+        .map_err(|source| ProverError::ServerConnectionFailed {
+            host: server_host.to_string(),
+            port: server_port,
+            source,
+        })?;
 
-    println!("Connected to server");
+    debug!("Connected to server");
 
-    let (mpc_tls_connection, prover_fut) = prover.connect(client_socket).await?;
+    let (mpc_tls_connection, prover_fut) = prover
+        .connect(client_socket)
+        .await
+        .map_err(ProverError::MpcSetupFailed)?;
     let mpc_tls_connection = FuturesIo::new(mpc_tls_connection);
 
-    println!("MPC connect");
+    debug!("MPC-TLS connection set up");
 
     let prover_task = smol::spawn(prover_fut);
 
     let (mut request_sender, connection) =
-        hyper::client::conn::http1::handshake(mpc_tls_connection).await?;
+        hyper::client::conn::http1::handshake(mpc_tls_connection)
+            .await
+            .map_err(ProverError::HyperError)?;
 
     smol::spawn(connection).detach();
 
-    println!("Starting an MPC TLS connection with the server");
+    debug!("Sending the request over MPC-TLS");
 
-    let response = request_sender.send_request(request).await?;
+    let response = request_sender
+        .send_request(request)
+        .await
+        .map_err(ProverError::HyperError)?;
 
-    println!("Got a response from the server: {:?}", response);
+    info!(status = %response.status(), "Got a response from the server");
 
     if response.status() != StatusCode::OK {
-        return Err(ProverError::NotarizationFailed(format!(
-            "Server responded with status: {}",
-            response.status()
-        )));
+        return Err(ProverError::UnexpectedStatus(response.status().as_u16()).into());
     }
 
     // The prover task should be done now, so we can await it.
     let prover = prover_task
         .await
-        .map_err(|e| ProverError::NotarizationFailed(format!("Prover task failed: {}", e)))?;
+        .map_err(|e| ProverError::NotarizationFailed(e.into()))?;
 
     // Parse the transcript using your custom parsers to get ranges
-    let (prover, recv_ranges) = redact_and_reveal_received_data(prover).await;
-    let (mut prover, sent_ranges) = redact_and_reveal_sent_data(prover).await;
+    let (prover, recv_ranges) = redact_and_reveal_received_data(prover, policy).await?;
+    let (mut prover, sent_ranges) = redact_and_reveal_sent_data(prover, policy).await?;
+
+    if recv_ranges.is_empty() {
+        return Err(ProverError::NothingToDisclose.into());
+    }
 
     // Commit to the transcript using your custom ranges
     let mut builder = TranscriptCommitConfig::builder(prover.transcript());
 
-    // Commit the ranges identified by your parsing functions
-    // for range in &recv_ranges {
-    //     builder
-    //         .commit_recv(range)
-    //         .map_err(|e| ProverError::NotarizationFailed(e.to_string()))?;
-    // }
-
-    // for range in &recv_ranges {
-    //     builder
-    //         .commit_with_kind(range, Direction::Received, TranscriptCommitmentKind::Hash { alg: HashAlgId::SHA256 })
-    //         .map_err(|e| ProverError::NotarizationFailed(e.to_string()))?;
-    // }
-
-    let full_range_received = 0..prover.transcript().received().len();
-    builder
-        .commit_with_kind(
-            &full_range_received,
-            Direction::Received,
-            TranscriptCommitmentKind::Hash {
-                alg: HashAlgId::SHA256,
-            },
-        )
-        .map_err(|e| ProverError::NotarizationFailed(e.to_string()))?;
-
-    // let full_range_sent = 0..prover.transcript().sent().len();
-    // builder
-    //     .commit_with_kind(&full_range_sent, Direction::Sent, TranscriptCommitmentKind::Hash { alg: HashAlgId::SHA256 })
-    //     .map_err(|e| ProverError::NotarizationFailed(e.to_string()))?;
-
-    // for range in &sent_ranges {
-    //     builder
-    //         .commit_with_kind(range, Direction::Sent, TranscriptCommitmentKind::Hash { alg: HashAlgId::SHA256 })
-    //         .map_err(|e| ProverError::NotarizationFailed(e.to_string()))?;
-    // }
+    // Each field gets its own hash commitment, so it can be opened on its own
+    // and everything else stays hidden
+    for range in &recv_ranges {
+        builder
+            .commit_with_kind(
+                range,
+                Direction::Received,
+                commitment_kind(policy.hash_algorithm),
+            )
+            .map_err(|e| ProverError::NotarizationFailed(e.into()))?;
+    }
 
-    // for range in &sent_ranges {
-    //     builder
-    //         .commit_sent(range)
-    //         .map_err(|e| ProverError::NotarizationFailed(e.to_string()))?;
-    // }
+    for range in &sent_ranges {
+        builder
+            .commit_with_kind(
+                range,
+                Direction::Sent,
+                commitment_kind(policy.hash_algorithm),
+            )
+            .map_err(|e| ProverError::NotarizationFailed(e.into()))?;
+    }
 
     let transcript_commit = builder
         .build()
-        .map_err(|e| ProverError::NotarizationFailed(e.to_string()))?;
+        .map_err(|e| ProverError::NotarizationFailed(e.into()))?;
 
     // Build an attestation request.
     let mut builder = RequestConfig::builder();
     builder.transcript_commit(transcript_commit);
     let request_config = builder
         .build()
-        .map_err(|e| ProverError::NotarizationFailed(e.to_string()))?;
+        .map_err(|e| ProverError::NotarizationFailed(e.into()))?;
 
     #[allow(deprecated)]
-    let (attestation, secrets) = prover.notarize(&request_config).await?;
+    let (attestation, secrets) = prover
+        .notarize(&request_config)
+        .await
+        .map_err(ProverError::TlsnProverError)?;
 
-    println!("Notarization complete!");
+    info!(%session_id, "Notarization complete");
 
-    Ok((attestation, secrets))
+    Ok((attestation, secrets, session_id))
 }
 
 /// Create a presentation from attestation and secrets
 pub async fn create_presentation(
     attestation: Attestation,
     secrets: Secrets,
+    policy: &DisclosurePolicy,
 ) -> Result<Presentation> {
-    let recv_ranges = get_received_data_ranges(&secrets);
-    // let sent_ranges = get_sent_data_ranges(&secrets);
+    let recv_ranges = get_received_data_ranges(&secrets, policy)?;
+    let sent_ranges = get_sent_data_ranges(&secrets, policy)?;
 
-    println!("recv_ranges: {:?}", recv_ranges);
-    // println!("sent_ranges: {:?}", sent_ranges);
+    debug!(?recv_ranges, ?sent_ranges, "Ranges to reveal");
 
-    let builder = secrets.transcript_proof_builder();
+    if recv_ranges.is_empty() {
+        return Err(ProverError::NothingToDisclose.into());
+    }
+
+    let mut builder = secrets.transcript_proof_builder();
 
-    let builder = {
-        let mut builder = builder;
-        let full_range_received = 0..secrets.transcript().received().len();
+    // Reveal exactly the ranges committed during notarization
+    for range in &recv_ranges {
         builder
-            .reveal_recv(&full_range_received)
-            .map_err(|e| ProverError::PresentationCreationFailed(e.to_string()))?;
-
-        // let full_range_sent = 0..secrets.transcript().sent().len();
-        // let mut sent_string = String::new();
-        // secrets.transcript().sent().read_to_string(&mut sent_string);
-        // println!("Sent data: {}", sent_string);
-        // builder.reveal_sent(&full_range_sent).map_err(|e| ProverError::PresentationCreationFailed(e.to_string()))?;
+            .reveal_with_kind(
+                range,
+                Direction::Received,
+                commitment_kind(policy.hash_algorithm),
+            )
+            .map_err(|e| ProverError::PresentationCreationFailed(e.into()))?;
+    }
+
+    for range in &sent_ranges {
         builder
-    };
+            .reveal_with_kind(
+                range,
+                Direction::Sent,
+                commitment_kind(policy.hash_algorithm),
+            )
+            .map_err(|e| ProverError::PresentationCreationFailed(e.into()))?;
+    }
 
     let transcript_proof = builder
         .build()
-        .map_err(|e| ProverError::PresentationCreationFailed(e.to_string()))?;
+        .map_err(|e| ProverError::PresentationCreationFailed(e.into()))?;
     // Use default crypto provider to build the presentation.
     let provider = CryptoProvider::default();
 
@@ -265,13 +280,13 @@ pub async fn create_presentation(
         .identity_proof(secrets.identity_proof())
         .transcript_proof(transcript_proof);
 
-    println!("building presentation");
+    debug!("Building presentation");
 
     let presentation: Presentation = builder
         .build()
-        .map_err(|e| ProverError::PresentationCreationFailed(e.to_string()))?;
+        .map_err(|e| ProverError::PresentationCreationFailed(e.into()))?;
 
-    println!("Presentation built successfully!");
+    info!("Presentation built");
 
     Ok(presentation)
 }
@@ -280,115 +295,60 @@ pub async fn create_presentation(
 ///
 /// # Arguments
 /// * `provider` - Object that provides transcript data
+/// * `policy` - Which response fields to reveal
 ///
 /// # Returns
 /// * `Vec<Range<usize>>` - The ranges to reveal
-pub fn get_received_data_ranges<T: TranscriptProvider>(provider: &T) -> Vec<Range<usize>> {
-    // Get the received transcript data
-    let recv_transcript = provider.received_data();
-
-    // Convert to a UTF-8 string
-    let recv_string = match String::from_utf8(recv_transcript.to_vec()) {
-        Ok(s) => s,
-        Err(e) => {
-            println!("Failed to convert received data to UTF-8: {}", e);
-            return Vec::new();
-        }
-    };
-
-    println!("Received data: {}", recv_string);
-
-    // Parse the response
-    let parse = match ResponseParser::parse(ResponseRule::response, &recv_string) {
-        Ok(p) => p,
-        Err(e) => {
-            println!("Failed to parse response: {}", e);
-            return Vec::new();
-        }
-    };
-
-    // Convert the parse result to a Response object
-    let response = match Response::try_from(parse) {
-        Ok(r) => r,
-        Err(e) => {
-            println!("Failed to convert parse result to Response: {}", e);
-            return Vec::new();
-        }
-    };
-
-    // Get the ranges to reveal
-    response.get_all_ranges_for_keypaths(
-        &["comment", "currency", "amount", "recipient.username"],
-        &[],
-    )
+pub fn get_received_data_ranges<T: TranscriptProvider>(
+    provider: &T,
+    policy: &DisclosurePolicy,
+) -> std::result::Result<Vec<Range<usize>>, ParserError> {
+    disclosure::received_ranges(provider.received_data(), policy)
 }
 
 /// Redacts and reveals sent data to the verifier
 ///
 /// # Arguments
 /// * `provider` - Object that provides transcript data
+/// * `policy` - Which request fields to reveal
 ///
 /// # Returns
 /// * `Vec<Range<usize>>` - The ranges to reveal
-pub fn get_sent_data_ranges<T: TranscriptProvider>(provider: &T) -> Vec<Range<usize>> {
-    // Get the sent transcript data
-    let sent_transcript = provider.sent_data();
-
-    // Convert to a UTF-8 string
-    let sent_string = match String::from_utf8(sent_transcript.to_vec()) {
-        Ok(s) => s,
-        Err(e) => {
-            println!("Failed to convert sent data to UTF-8: {}", e);
-            return Vec::new();
-        }
-    };
-
-    // Parse the request
-    let parse = match RequestParser::parse(RequestRule::request, &sent_string) {
-        Ok(p) => p,
-        Err(e) => {
-            println!("Failed to parse request: {}", e);
-            return Vec::new();
-        }
-    };
-
-    // Convert the parse result to a Request object
-    let request = match Request::try_from(parse) {
-        Ok(r) => r,
-        Err(e) => {
-            println!("Failed to convert parse result to Request: {}", e);
-            return Vec::new();
-        }
-    };
-
-    // Get the ranges to reveal
-    request.get_all_ranges_for_keypaths(&[], &[])
+pub fn get_sent_data_ranges<T: TranscriptProvider>(
+    provider: &T,
+    policy: &DisclosurePolicy,
+) -> std::result::Result<Vec<Range<usize>>, ParserError> {
+    disclosure::sent_ranges(provider.sent_data(), policy)
 }
 
 /// Redacts and reveals received data to the verifier (legacy function for Prover)
 ///
 /// # Arguments
 /// * `prover` - The prover object to work with
+/// * `policy` - Which response fields to reveal
 ///
 /// # Returns
 /// * `(Prover<Committed>, Vec<Range<usize>>)` - The prover and the ranges
 pub async fn redact_and_reveal_received_data(
     prover: Prover<Committed>,
-) -> (Prover<Committed>, Vec<Range<usize>>) {
-    let ranges = get_received_data_ranges(&prover);
-    (prover, ranges)
+    policy: &DisclosurePolicy,
+) -> std::result::Result<(Prover<Committed>, Vec<Range<usize>>), ParserError> {
+    let ranges = get_received_data_ranges(&prover, policy)?;
+    Ok((prover, ranges))
 }
 
 /// Redacts and reveals sent data to the verifier (legacy function for Prover)
 ///
 /// # Arguments
 /// * `prover` - The prover object to work with
+/// * `policy` - Which request fields to reveal
 ///
 /// # Returns
 /// * `(Prover<Committed>, Vec<Range<usize>>)` - The prover and the ranges
 pub async fn redact_and_reveal_sent_data(
     prover: Prover<Committed>,
-) -> (Prover<Committed>, Vec<Range<usize>>) {
-    let ranges = get_sent_data_ranges(&prover);
-    (prover, ranges)
+    policy: &DisclosurePolicy,
+) -> std::result::Result<(Prover<Committed>, Vec<Range<usize>>), ParserError> {
+    let ranges = get_sent_data_ranges(&prover, policy)?;
+    Ok((prover, ranges))
 }
@@ -0,0 +1,306 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use tlsn_core::{
+    connection::ServerName,
+    presentation::{Presentation, PresentationOutput},
+    signing::VerifyingKey,
+    transcript::PartialTranscript,
+};
+
+use crate::ast::RangedValue;
+use crate::errors::{Error, VerifierError};
+use crate::redacted::{parse_redacted_request_line, parse_redacted_response};
+use crate::trust::TrustStore;
+use tracing::{debug, info, warn};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevolutTransaction {
+    pub transaction_id: String,
+    pub state: String,
+    /// `None` when the server sent an explicit `null`, i.e. no comment was attached
+    pub comment: Option<String>,
+    pub currency: String,
+    pub amount: i64,
+    pub beneficiary: BeneficiaryType,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BeneficiaryType {
+    External {
+        iban: String,
+        bic: String,
+    },
+    Internal {
+        id: String,
+        #[serde(rename = "type")]
+        account_type: String,
+        username: String,
+        code: String,
+    },
+}
+
+/// Verify a serialized presentation and return the transaction it discloses
+pub async fn verify(
+    presentation: Vec<u8>,
+    trust_store: &TrustStore,
+) -> Result<Option<RevolutTransaction>, Error> {
+    info!("Verifying presentation");
+
+    let presentation =
+        bincode::deserialize(&presentation).map_err(VerifierError::DeserializationFailed)?;
+
+    let (transcript, session_info) = verify_presentation(presentation, trust_store).await?;
+
+    info!(server_name = ?session_info, "Presentation verified");
+    debug!(
+        sent = %bytes_to_redacted_string(transcript.sent_unsafe()),
+        "Disclosed request"
+    );
+
+    let transaction: Option<RevolutTransaction> = parse_transaction(&transcript);
+    debug!(?transaction, "Parsed disclosed transaction");
+
+    Ok(transaction)
+}
+
+/// Verify a presentation and return the disclosed transcript with the server name
+pub async fn verify_presentation(
+    presentation: Presentation,
+    trust_store: &TrustStore,
+) -> Result<(PartialTranscript, ServerName), Error> {
+    // Use `TrustStore::test_only()` for offline testing with the server-fixture.
+    let crypto_provider = trust_store.crypto_provider()?;
+
+    let VerifyingKey {
+        alg,
+        data: key_data,
+    } = presentation.verifying_key();
+
+    // Only the caller can decide whether this notary key is trusted
+    info!(%alg, key = %hex::encode(key_data), "Verifying presentation with notary key");
+
+    // Verify the presentation.
+    let PresentationOutput {
+        server_name,
+        transcript,
+        ..
+    } = presentation
+        .verify(&crypto_provider)
+        .map_err(VerifierError::VerificationFailed)?;
+
+    let server_name = server_name.ok_or(VerifierError::MissingServerName)?;
+    let transcript = transcript.ok_or(VerifierError::MissingTranscript)?;
+
+    Ok((transcript, server_name))
+}
+
+/// Render redacted bytes as `🙈`.
+pub fn bytes_to_redacted_string(bytes: &[u8]) -> String {
+    String::from_utf8(bytes.to_vec())
+        .unwrap_or_else(|_| String::from("Invalid UTF-8"))
+        .replace('\0', "🙈")
+}
+
+/// Extract the Revolut transaction from the disclosed parts of the transcript
+///
+/// The request line and response are parsed with the redaction-aware grammars, so
+/// only values whose bytes and enclosing structure were disclosed are used. The
+/// response must have status `200`.
+pub fn parse_transaction(transcript: &PartialTranscript) -> Option<RevolutTransaction> {
+    let sent_authed: Vec<Range<usize>> = transcript.sent_authed().iter_ranges().collect();
+    let received_authed: Vec<Range<usize>> = transcript.received_authed().iter_ranges().collect();
+
+    parse_disclosed_transaction(
+        transcript.sent_unsafe(),
+        &sent_authed,
+        transcript.received_unsafe(),
+        &received_authed,
+    )
+}
+
+/// `parse_transaction` over transcripts whose undisclosed bytes are zero, given the
+/// ranges of each that were disclosed
+fn parse_disclosed_transaction(
+    sent: &[u8],
+    sent_authed: &[Range<usize>],
+    received: &[u8],
+    received_authed: &[Range<usize>],
+) -> Option<RevolutTransaction> {
+    // The request line is revealed, so the proof is bound to the requested transaction
+    let request_line = parse_redacted_request_line(sent, sent_authed)
+        .inspect_err(|e| warn!(error = %e, "Failed to parse the disclosed request line"))
+        .ok()?;
+    let transaction_id = parse_transaction_id(&request_line.url._value)?;
+
+    let response = parse_redacted_response(received, received_authed)
+        .inspect_err(|e| warn!(error = %e, "Failed to parse the disclosed response"))
+        .ok()?;
+    // Error responses carry no transaction, whatever their body looks like
+    if response.status_code() != Some(200) {
+        warn!(status = ?response.status_code(), "Response status is not 200");
+        return None;
+    }
+    let content = &response.content;
+
+    let state = required_string(content, "state")?;
+    let currency = required_string(content, "currency")?;
+    // A fraction or exponent is rejected, not truncated
+    let amount = required(content, "amount")?.as_i64()?;
+    // The revealed `"comment":null` proves that no comment was attached
+    let comment = match required(content, "comment")? {
+        value if value.is_null() => None,
+        value => Some(value.as_str()?.to_string()),
+    };
+
+    let iban = ["recipient.account.IBAN", "recipient.account.iban"]
+        .iter()
+        .find_map(|keypath| content.get_keypath(keypath)?.as_str());
+    let bic = ["recipient.account.BIC", "recipient.account.bic"]
+        .iter()
+        .find_map(|keypath| content.get_keypath(keypath)?.as_str());
+
+    let beneficiary = match (iban, bic) {
+        (Some(iban), Some(bic)) => BeneficiaryType::External {
+            iban: iban.to_string(),
+            bic: bic.to_string(),
+        },
+        _ => BeneficiaryType::Internal {
+            id: required_string(content, "recipient.id")?,
+            account_type: required_string(content, "recipient.type")?,
+            username: required_string(content, "recipient.username")?,
+            code: required_string(content, "recipient.code")?,
+        },
+    };
+
+    Some(RevolutTransaction {
+        transaction_id,
+        state,
+        currency,
+        amount,
+        comment,
+        beneficiary,
+    })
+}
+
+/// Transaction id from a request target such as `/api/retail/transaction/5`
+fn parse_transaction_id(target: &str) -> Option<String> {
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    let (_, id) = path.rsplit_once("/transaction/")?;
+
+    (!id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+    .then(|| id.to_string())
+}
+
+/// Disclosed value at a keypath, or `None` if it or its path was not disclosed
+fn required<'a>(content: &'a RangedValue, keypath: &str) -> Option<&'a RangedValue> {
+    match content.get_keypath(keypath) {
+        Some(value) if !matches!(value, RangedValue::Redacted { .. }) => Some(value),
+        _ => {
+            debug!(keypath, "Required value not disclosed");
+            None
+        }
+    }
+}
+
+/// Decoded string at a keypath
+fn required_string(content: &RangedValue, keypath: &str) -> Option<String> {
+    required(content, keypath)?.as_str().map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disclosure;
+    use crate::policy::DisclosurePolicy;
+
+    const REQUEST: &str = "GET /api/retail/transaction/7a2f0c1e-3b4d-4e5f-8a9b-0c1d2e3f4a5b HTTP/1.1\r\n\
+                           host: app.revolut.com\r\n\
+                           cookie: session=s3cr3t\r\n\
+                           x-device-id: d3v1c3\r\n\
+                           accept-encoding: identity\r\n\r\n";
+
+    const BODY: &str = r#"[{"id":"7a2f0c1e-3b4d-4e5f-8a9b-0c1d2e3f4a5b","legId":"9b8c7d6e-5f4a-4b3c-2d1e-0f9a8b7c6d5e","type":"TRANSFER","balance":123456,"state":"COMPLETED","startedDate":1760000000000,"updatedDate":1760000001000,"completedDate":1760000002000,"createdDate":1760000000000,"currency":"EUR","amount":-1050,"fee":0,"description":"To Alice","tag":"transfers","category":"transfers","account":{"id":"a1b2","type":"CURRENT"},"suggestions":[],"rate":1.0,"recipient":{"id":"r-42","type":"REVOLUT","username":"alice","code":"alice42","firstName":"Alice"},"comment":null}]"#;
+
+    /// Zero every byte outside the ranges, as in the transcript of a presentation
+    fn disclose(data: &[u8], ranges: &[Range<usize>]) -> Vec<u8> {
+        data.iter()
+            .enumerate()
+            .map(|(i, byte)| {
+                if ranges.iter().any(|range| range.contains(&i)) {
+                    *byte
+                } else {
+                    0
+                }
+            })
+            .collect()
+    }
+
+    /// Disclose the transcripts with the default policy and parse the transaction
+    fn round_trip(response: &str) -> Option<RevolutTransaction> {
+        let policy = DisclosurePolicy::revolut_transaction();
+        let sent_ranges = disclosure::sent_ranges(REQUEST.as_bytes(), &policy).unwrap();
+        let received_ranges = disclosure::received_ranges(response.as_bytes(), &policy).unwrap();
+
+        parse_disclosed_transaction(
+            &disclose(REQUEST.as_bytes(), &sent_ranges),
+            &sent_ranges,
+            &disclose(response.as_bytes(), &received_ranges),
+            &received_ranges,
+        )
+    }
+
+    fn assert_transaction(transaction: RevolutTransaction) {
+        assert_eq!(
+            transaction.transaction_id,
+            "7a2f0c1e-3b4d-4e5f-8a9b-0c1d2e3f4a5b"
+        );
+        assert_eq!(transaction.state, "COMPLETED");
+        assert_eq!(transaction.currency, "EUR");
+        assert_eq!(transaction.amount, -1050);
+        assert_eq!(transaction.comment, None);
+        match transaction.beneficiary {
+            BeneficiaryType::Internal {
+                id,
+                account_type,
+                username,
+                code,
+            } => {
+                assert_eq!(id, "r-42");
+                assert_eq!(account_type, "REVOLUT");
+                assert_eq!(username, "alice");
+                assert_eq!(code, "alice42");
+            }
+            beneficiary => panic!("unexpected beneficiary {beneficiary:?}"),
+        }
+    }
+
+    #[test]
+    fn default_policy_round_trip() {
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            BODY.len(),
+            BODY
+        );
+        assert_transaction(round_trip(&response).unwrap());
+    }
+
+    #[test]
+    fn default_policy_round_trip_chunked() {
+        // Chunk boundaries inside the hidden balance and the disclosed amount
+        let (first, rest) = BODY.split_at(BODY.find("3456").unwrap());
+        let (second, third) = rest.split_at(rest.find("050").unwrap());
+        let mut response =
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ntransfer-encoding: chunked\r\n\r\n"
+                .to_string();
+        for chunk in [first, second, third] {
+            response.push_str(&format!("{:x}\r\n{}\r\n", chunk.len(), chunk));
+        }
+        response.push_str("0\r\n\r\n");
+
+        assert_transaction(round_trip(&response).unwrap());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::errors::WitnessError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tlsn_core::{
+    attestation::{Extension, Field, Header},
+    connection::{ConnectionInfo, ServerCertCommitment, ServerEphemKey},
+    hash::{Blinder, Hash, HashAlgId},
+    presentation::Presentation,
+    signing::{Signature, VerifyingKey},
+    transcript::{
+        Direction, Idx, PartialTranscript, TranscriptCommitment, hash::PlaintextHashSecret,
+    },
+};
+
+/// Build the Noir circuit witness for a presentation
+///
+/// The witness is a flat list of bytes, in this order:
+///
+/// 1. the notary key without its SEC1 tag (64 bytes)
+/// 2. the BCS-encoded attestation header and its signature
+/// 3. the header Merkle root without its length prefix
+/// 4. the BCS-encoded verifying key, connection info, server ephemeral key and
+///    certificate commitment fields
+/// 5. for every hash commitment in the attestation, in attestation order, the
+///    BCS-encoded commitment without its field id, the committed plaintext and the
+///    16-byte blinder
+///
+/// The number of commitments and the length of each plaintext depend on the
+/// disclosure policy, so the circuit must be compiled for the same set of fields.
+pub fn build_witness(presentation: &Presentation) -> Result<Vec<String>, WitnessError> {
+    let mut witness: Vec<String> = vec![];
+
+    // Mirror the presentation into structs with public fields
+    let json = serde_json::to_value(presentation)?;
+    let attestation: AttestationProof = serde_json::from_str(&json["attestation"].to_string())?;
+    let transcript: TranscriptProof = serde_json::from_str(&json["transcript"].to_string())?;
+
+    let key_ecdsa =
+        k256::ecdsa::VerifyingKey::from_sec1_bytes(&attestation.body.body.verifying_key.data.data)?;
+    let key = key_ecdsa.to_encoded_point(false).as_bytes().to_vec();
+
+    let signature = attestation.signature.data;
+
+    let message = bcs::to_bytes(&attestation.header)?;
+
+    let header_root = bcs::to_bytes(&attestation.header.root.value)?;
+
+    let verifying_key_data = bcs::to_bytes(&attestation.body.body.verifying_key.data)?;
+    let connection_info_data = bcs::to_bytes(&attestation.body.body.connection_info.data)?;
+    let server_ephemeral_key_data =
+        bcs::to_bytes(&attestation.body.body.server_ephemeral_key.data)?;
+    let cert_commitment_data = bcs::to_bytes(&attestation.body.body.cert_commitment.data)?;
+
+    witness.extend(key[1..].iter().map(|n| n.to_string()));
+    witness.extend(message.iter().map(|n| n.to_string()));
+    witness.extend(signature.iter().map(|n| n.to_string()));
+    witness.extend(header_root[1..].iter().map(|n| n.to_string()));
+    witness.extend(verifying_key_data.iter().map(|n| n.to_string()));
+    witness.extend(connection_info_data.iter().map(|n| n.to_string()));
+    witness.extend(server_ephemeral_key_data.iter().map(|n| n.to_string()));
+    witness.extend(cert_commitment_data.iter().map(|n| n.to_string()));
+
+    // Each disclosed field has its own hash commitment, so only the committed bytes
+    // are opened instead of the whole received transcript. Secrets are matched to
+    // their commitment by direction and index, not by position.
+    let mut unmatched: Vec<&PlaintextHashSecret> = transcript.hash_secrets.iter().collect();
+    for (position, commitment) in attestation
+        .body
+        .body
+        .transcript_commitments
+        .iter()
+        .enumerate()
+    {
+        let TranscriptCommitment::Hash(hash) = &commitment.data else {
+            continue;
+        };
+        let secret = unmatched
+            .iter()
+            .position(|secret| secret.direction == hash.direction && secret.idx == hash.idx)
+            .map(|i| unmatched.swap_remove(i))
+            .ok_or(WitnessError::MissingHashSecret {
+                direction: hash.direction,
+                position,
+            })?;
+
+        let commitment_data = bcs::to_bytes(commitment)?;
+
+        let data = match secret.direction {
+            Direction::Sent => transcript.transcript.sent_unsafe(),
+            Direction::Received => transcript.transcript.received_unsafe(),
+        };
+        let plaintext = secret
+            .idx
+            .iter()
+            .map(|index| {
+                data.get(index)
+                    .copied()
+                    .ok_or(WitnessError::IndexOutOfBounds {
+                        index,
+                        len: data.len(),
+                    })
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+
+        witness.extend(commitment_data[4..].iter().map(|n| n.to_string()));
+        witness.extend(plaintext.iter().map(|n| n.to_string()));
+        witness.extend(secret.blinder.as_bytes().iter().map(|n| n.to_string()));
+    }
+
+    if let Some(secret) = unmatched.first() {
+        return Err(WitnessError::MissingHashCommitment {
+            direction: secret.direction,
+        });
+    }
+
+    Ok(witness)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestationProof {
+    pub signature: Signature,
+    pub header: Header,
+    pub body: BodyProof,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyProof {
+    pub body: Body,
+    pub proof: MerkleProof,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MerkleProof {
+    pub alg: HashAlgId,
+    pub leaf_count: usize,
+    pub proof: rs_merkle::MerkleProof<Hash>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Body {
+    pub verifying_key: Field<VerifyingKey>,
+    pub connection_info: Field<ConnectionInfo>,
+    pub server_ephemeral_key: Field<ServerEphemKey>,
+    pub cert_commitment: Field<ServerCertCommitment>,
+    pub extensions: Vec<Field<Extension>>,
+    pub transcript_commitments: Vec<Field<TranscriptCommitment>>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TranscriptProof {
+    pub transcript: PartialTranscript,
+    pub encoding_proof: Option<EncodingProof>,
+    pub hash_secrets: Vec<PlaintextHashSecret>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncodingProof {
+    pub inclusion_proof: MerkleProof,
+    pub openings: HashMap<usize, Opening>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Opening {
+    pub direction: Direction,
+    pub idx: Idx,
+    pub blinder: Blinder,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TranscriptOpening {
+    pub id: usize,
+    pub direction: usize,
+    pub data: Vec<u8>,
+    pub blinder: Vec<u8>,
+    pub position: usize,
+}